@@ -3,17 +3,23 @@ pub mod structures;
 
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
 use anyhow::bail;
 use anyhow::{anyhow, ensure, Context};
 use serde::{Serialize, Deserialize};
-use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
-use sha2::Sha256;
 
 use reqwest::Client;
 use reqwest::Response;
 use reqwest::header::{HeaderMap, HeaderValue};
-use utils::get_timestamp;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use utils::{get_timestamp, hmac_sha256_hex, is_uuid, split_time_range};
 use structures::*;
 
 
@@ -22,9 +28,27 @@ pub const REST_API_URL: &str = "https://api.bybit.com";
 // hk https://api.byhkbit.com
 // tr https://api.bybit-tr.com
 
+pub const PRIVATE_WS_URL: &str = "wss://stream.bybit.com/v5/private";
+
 pub const RECV_WINDOW: &str = "5000";
 
-#[derive(Serialize, PartialEq, Clone, Copy)]
+// bybit closes websockets that stay silent for longer than this
+const WS_PING_INTERVAL: Duration = Duration::from_secs(20);
+
+// retCodes bybit returns when a setting already has the requested value, each only
+// meaningful for its own endpoint
+pub const POSITION_MODE_NOT_MODIFIED: u64 = 110025;
+pub const MARGIN_MODE_NOT_MODIFIED: u64 = 110026;
+pub const LEVERAGE_NOT_MODIFIED: u64 = 110043;
+pub const RISK_ID_NOT_MODIFIED: u64 = 110075;
+
+// widest startTime/endTime range accepted by the history endpoints (7 days)
+pub const QUERY_WINDOW_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+// deposit and withdrawal records allow 30 days
+pub const ASSET_QUERY_WINDOW_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+#[derive(Serialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum Category {
     Spot,
@@ -33,7 +57,7 @@ pub enum Category {
     Inverse
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
 pub enum TradeDirection {
     Buy,
     Sell
@@ -48,7 +72,7 @@ impl fmt::Display for TradeDirection {
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Copy)]
 pub enum OrderType {
     Market,
     Limit
@@ -64,12 +88,193 @@ impl fmt::Display for OrderType {
 }
 
 // https://bybit-exchange.github.io/docs/v5/enum#timeinforce
-#[derive(Serialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
 pub enum TimeInForce {
     PostOnly,
     GTC,
     IOC,
-    FOK
+    FOK,
+    RPI
+}
+
+// unit of qty for spot market orders
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum MarketUnit {
+    BaseCoin,
+    QuoteCoin
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#smptype
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum SmpType {
+    None,
+    CancelMaker,
+    CancelTaker,
+    CancelBoth
+}
+
+// slippage cap for market orders, TickSize in ticks or Percent of the last price
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SlippageTolerance {
+    TickSize(f64),
+    Percent(f64)
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#tpslmode
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum TpslMode {
+    Full,
+    Partial
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#triggerby
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum TriggerBy {
+    LastPrice,
+    IndexPrice,
+    MarkPrice
+}
+
+// direction the price must cross for a conditional order to trigger
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriggerDirection {
+    RisesTo = 1,
+    FallsTo = 2
+}
+
+impl<'de> Deserialize<'de> for TriggerDirection {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match i32::deserialize(deserializer)? {
+            1 => Ok(TriggerDirection::RisesTo),
+            2 => Ok(TriggerDirection::FallsTo),
+            other => Err(serde::de::Error::custom(format!("invalid triggerDirection {other}"))),
+        }
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#orderfilter
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum OrderFilter {
+    Order,
+    #[serde(rename = "tpslOrder")]
+    TpslOrder,
+    StopOrder,
+    OcoOrder,
+    BidirectionalTpslOrder
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#positionidx
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum PositionIdx {
+    OneWay = 0,
+    HedgeBuy = 1,
+    HedgeSell = 2
+}
+
+impl PositionIdx {
+    /// The hedge-mode slot trading `side` opens into.
+    pub fn hedge(side: TradeDirection) -> Self {
+        match side {
+            TradeDirection::Buy => PositionIdx::HedgeBuy,
+            TradeDirection::Sell => PositionIdx::HedgeSell,
+        }
+    }
+}
+
+impl Serialize for PositionIdx {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(*self as i32)
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionIdx {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match i32::deserialize(deserializer)? {
+            0 => Ok(PositionIdx::OneWay),
+            1 => Ok(PositionIdx::HedgeBuy),
+            2 => Ok(PositionIdx::HedgeSell),
+            other => Err(serde::de::Error::custom(format!("invalid positionIdx {other}"))),
+        }
+    }
+}
+
+// side of an open position, None when flat
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum PositionSide {
+    Buy,
+    Sell,
+    #[serde(alias = "")]
+    None
+}
+
+// https://bybit-exchange.github.io/docs/v5/position/position-mode
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PositionMode {
+    OneWay = 0,
+    Hedge = 3
+}
+
+// https://bybit-exchange.github.io/docs/v5/position/cross-isolate
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TradeMode {
+    Cross = 0,
+    Isolated = 1
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#stopordertype
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum StopOrderType {
+    TakeProfit,
+    StopLoss,
+    TrailingStop,
+    Stop,
+    PartialTakeProfit,
+    PartialStopLoss,
+    #[serde(rename = "tpslOrder")]
+    TpslOrder,
+    OcoOrder,
+    MmRateClose,
+    BidirectionalTpslOrder,
+    #[serde(other)]
+    Unknown
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#exectype
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ExecType {
+    Trade,
+    AdlTrade,
+    Funding,
+    BustTrade,
+    Delivery,
+    Settle,
+    BlockTrade,
+    MovePosition,
+    #[serde(other)]
+    Unknown
+}
+
+pub enum PositionScope {
+    Symbol(String),
+    BaseCoin(String),
+    SettleCoin(String)
+}
+
+pub enum OrderScope {
+    Symbol(String),
+    BaseCoin(String),
+    SettleCoin(String)
+}
+
+// openOnly of https://bybit-exchange.github.io/docs/v5/order/open-order
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub enum OrderListing {
+    #[serde(rename = "0")]
+    OpenOrders,
+    // the last 500 closed orders, no open ones
+    #[serde(rename = "1")]
+    RecentlyClosed
 }
 
 pub enum OrderId {
@@ -78,15 +283,340 @@ pub enum OrderId {
 }
 
 
-#[derive(Serialize, Deserialize, Debug)]
+// https://bybit-exchange.github.io/docs/v5/enum#type
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionType {
+    TransferIn,
+    TransferOut,
+    Trade,
+    Settlement,
+    Delivery,
+    Liquidation,
+    Adl,
+    Airdrop,
+    Bonus,
+    BonusRecollect,
+    FeeRefund,
+    Interest,
+    CurrencyBuy,
+    CurrencySell,
+    SpotRepaymentSell,
+    SpotRepaymentBuy,
+    AutoDeduction,
+    #[serde(other)]
+    Other
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#marginmode
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MarginMode {
+    IsolatedMargin,
+    RegularMargin,
+    PortfolioMargin
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#status
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransferStatus {
+    Success,
+    Pending,
+    Failed,
+    #[serde(other)]
+    StatusUnknown
+}
+
+impl TransferStatus {
+    /// Whether the transfer has settled one way or the other and polling can stop.
+    pub fn is_final(&self) -> bool {
+        matches!(self, TransferStatus::Success | TransferStatus::Failed)
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/convert/convert-coin-list
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ConvertAccountType {
+    #[serde(rename = "eb_convert_funding")]
+    Funding,
+    #[serde(rename = "eb_convert_uta")]
+    Unified,
+    #[serde(rename = "eb_convert_spot")]
+    Spot,
+    #[serde(rename = "eb_convert_contract")]
+    Contract,
+    #[serde(rename = "eb_convert_inverse")]
+    Inverse
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConvertSide {
+    From = 0,
+    To = 1
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertStatus {
+    Init,
+    Processing,
+    Success,
+    Failure,
+    #[serde(other)]
+    Unknown
+}
+
+impl ConvertStatus {
+    /// Whether the conversion has settled one way or the other and polling can stop.
+    pub fn is_final(&self) -> bool {
+        matches!(self, ConvertStatus::Success | ConvertStatus::Failure)
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/create-subuid-apikey
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum ApiPermission {
+    ContractOrder,
+    ContractPosition,
+    SpotTrade,
+    OptionsTrade,
+    DerivativesTrade,
+    AccountTransfer,
+    SubMemberTransfer,
+    SubMemberTransferList,
+    Withdraw,
+    ExchangeHistory,
+    CopyTrading,
+    BlockTrade,
+    Earn
+}
+
+impl ApiPermission {
+    pub const ALL: [ApiPermission; 13] = [
+        ApiPermission::ContractOrder,
+        ApiPermission::ContractPosition,
+        ApiPermission::SpotTrade,
+        ApiPermission::OptionsTrade,
+        ApiPermission::DerivativesTrade,
+        ApiPermission::AccountTransfer,
+        ApiPermission::SubMemberTransfer,
+        ApiPermission::SubMemberTransferList,
+        ApiPermission::Withdraw,
+        ApiPermission::ExchangeHistory,
+        ApiPermission::CopyTrading,
+        ApiPermission::BlockTrade,
+        ApiPermission::Earn,
+    ];
+
+    /// `(group, name)` as bybit lists it in the permissions object.
+    pub fn as_parts(&self) -> (&'static str, &'static str) {
+        match self {
+            ApiPermission::ContractOrder => ("ContractTrade", "Order"),
+            ApiPermission::ContractPosition => ("ContractTrade", "Position"),
+            ApiPermission::SpotTrade => ("Spot", "SpotTrade"),
+            ApiPermission::OptionsTrade => ("Options", "OptionsTrade"),
+            ApiPermission::DerivativesTrade => ("Derivatives", "DerivativesTrade"),
+            ApiPermission::AccountTransfer => ("Wallet", "AccountTransfer"),
+            ApiPermission::SubMemberTransfer => ("Wallet", "SubMemberTransfer"),
+            ApiPermission::SubMemberTransferList => ("Wallet", "SubMemberTransferList"),
+            ApiPermission::Withdraw => ("Wallet", "Withdraw"),
+            ApiPermission::ExchangeHistory => ("Exchange", "ExchangeHistory"),
+            ApiPermission::CopyTrading => ("CopyTrading", "CopyTrading"),
+            ApiPermission::BlockTrade => ("BlockTrade", "BlockTrade"),
+            ApiPermission::Earn => ("Earn", "Earn"),
+        }
+    }
+
+    pub fn from_parts(group: &str, name: &str) -> Option<Self> {
+        ApiPermission::ALL.into_iter().find(|p| p.as_parts() == (group, name))
+    }
+
+    /// Whether the permission lets the key place or manage orders.
+    pub fn is_trading(&self) -> bool {
+        matches!(self, ApiPermission::ContractOrder | ApiPermission::ContractPosition | ApiPermission::SpotTrade
+            | ApiPermission::OptionsTrade | ApiPermission::DerivativesTrade)
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/subuid-list
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SubMemberType {
+    Normal = 1,
+    Custodial = 6
+}
+
+impl<'de> Deserialize<'de> for SubMemberType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match i32::deserialize(deserializer)? {
+            1 => Ok(SubMemberType::Normal),
+            6 => Ok(SubMemberType::Custodial),
+            other => Err(serde::de::Error::custom(format!("invalid memberType {other}"))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SubMemberStatus {
+    Normal = 1,
+    LoginBanned = 2,
+    Frozen = 4
+}
+
+impl<'de> Deserialize<'de> for SubMemberStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match i32::deserialize(deserializer)? {
+            1 => Ok(SubMemberStatus::Normal),
+            2 => Ok(SubMemberStatus::LoginBanned),
+            4 => Ok(SubMemberStatus::Frozen),
+            other => Err(serde::de::Error::custom(format!("invalid sub member status {other}"))),
+        }
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#depositstatus
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DepositStatus {
+    Unknown = 0,
+    ToBeConfirmed = 1,
+    Processing = 2,
+    Success = 3,
+    Failed = 4,
+    PendingToFund = 10011,
+    CreditedToFund = 10012
+}
+
+impl<'de> Deserialize<'de> for DepositStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match i64::deserialize(deserializer)? {
+            1 => DepositStatus::ToBeConfirmed,
+            2 => DepositStatus::Processing,
+            3 => DepositStatus::Success,
+            4 => DepositStatus::Failed,
+            10011 => DepositStatus::PendingToFund,
+            10012 => DepositStatus::CreditedToFund,
+            _ => DepositStatus::Unknown,
+        })
+    }
+}
+
+// internal (off-chain, bybit to bybit) deposits
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InternalDepositStatus {
+    Processing = 1,
+    Success = 2,
+    Failed = 3
+}
+
+impl<'de> Deserialize<'de> for InternalDepositStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match i64::deserialize(deserializer)? {
+            1 => Ok(InternalDepositStatus::Processing),
+            2 => Ok(InternalDepositStatus::Success),
+            3 => Ok(InternalDepositStatus::Failed),
+            other => Err(serde::de::Error::custom(format!("invalid internal deposit status {other}"))),
+        }
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#withdrawstatus
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum WithdrawStatus {
+    SecurityCheck,
+    Pending,
+    #[serde(rename = "success")]
+    Success,
+    CancelByUser,
+    Reject,
+    Fail,
+    BlockchainConfirmed,
+    MoreInformationRequired,
+    #[serde(other)]
+    Unknown
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WithdrawType {
+    OnChain = 0,
+    OffChain = 1,
+    All = 2
+}
+
+// wallet a withdrawal is paid from, https://bybit-exchange.github.io/docs/v5/asset/withdraw
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub enum WithdrawAccountType {
+    #[serde(rename = "UTA")]
+    Unified,
+    #[serde(rename = "FUND")]
+    Fund,
+    #[serde(rename = "SPOT")]
+    Spot,
+    // funding wallet first, the rest from the unified wallet
+    #[serde(rename = "FUND,UTA")]
+    FundThenUnified
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ForceChain {
+    // internal transfer when the address belongs to a bybit user
+    InternalIfPossible = 0,
+    OnChain = 1,
+    // address is a bybit uid
+    Uid = 2
+}
+
+// https://bybit-exchange.github.io/docs/v5/enum#accounttype
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum AccountType {
-    UNIFIED
+    UNIFIED,
+    CONTRACT,
+    SPOT,
+    FUND,
+    INVESTMENT
+}
+
+// https://bybit-exchange.github.io/docs/v5/order/dcp
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DcpProduct {
+    Options,
+    Derivatives,
+    Spot
 }
 
+impl DcpProduct {
+    // https://bybit-exchange.github.io/docs/v5/websocket/private/dcp
+    fn ws_topic(&self) -> &'static str {
+        match self {
+            DcpProduct::Options => "dcp.option",
+            DcpProduct::Derivatives => "dcp.future",
+            DcpProduct::Spot => "dcp.spot",
+        }
+    }
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 pub struct Bybit {
     pub api_key: Option<String>,
     pub api_secret: Option<String>,
-    pub client: Client
+    pub client: Client,
+    // private websocket holding the dcp subscription, see start_dcp_keepalive
+    dcp_keepalive: Mutex<Option<JoinHandle<()>>>,
+    // keyed by symbol, or base coin for options
+    fee_rates: Mutex<HashMap<(Category, String), FeeRate>>,
+    // withdrawals are refused until one is set
+    withdrawal_policy: Mutex<Option<WithdrawalPolicy>>
+}
+
+impl Drop for Bybit {
+    fn drop(&mut self) {
+        if let Ok(mut task) = self.dcp_keepalive.lock() {
+            if let Some(handle) = task.take() {
+                handle.abort();
+            }
+        }
+    }
 }
 
 impl Bybit {
@@ -105,15 +635,16 @@ impl Bybit {
         Ok(Self {
             api_key,
             api_secret,
-            client
+            client,
+            dcp_keepalive: Mutex::new(None),
+            fee_rates: Mutex::new(HashMap::default()),
+            withdrawal_policy: Mutex::new(None)
         })
     }
 
     pub fn sign_request(&self, request: String) -> anyhow::Result<String> {
         let secret_key = self.api_secret.as_ref().ok_or_else(|| anyhow!("Missing secret key"))?;
-        let mut signed_key = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes())?;
-        signed_key.update(request.as_bytes());
-        let signature = hex::encode(signed_key.finalize().into_bytes());
+        let signature = hmac_sha256_hex(secret_key, &request)?;
         //println!("signature: {signature}");
         Ok(signature)
     }
@@ -131,7 +662,7 @@ impl Bybit {
 
         let mut headers = HeaderMap::new();
         headers.insert("X-BAPI-SIGN", HeaderValue::from_str(signature)?);
-        headers.insert("X-BAPI-API-KEY", HeaderValue::from_str(&api_key)?);
+        headers.insert("X-BAPI-API-KEY", HeaderValue::from_str(api_key)?);
         headers.insert("X-BAPI-TIMESTAMP", HeaderValue::from_str(&timestamp.to_string())?);
         headers.insert("X-BAPI-RECV-WINDOW", HeaderValue::from_str(RECV_WINDOW)?);
         headers.insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -148,7 +679,7 @@ impl Bybit {
 
         let mut headers = HeaderMap::new();
         
-        headers.insert("X-BAPI-API-KEY", HeaderValue::from_str(&api_key)?);
+        headers.insert("X-BAPI-API-KEY", HeaderValue::from_str(api_key)?);
         headers.insert("X-BAPI-TIMESTAMP", HeaderValue::from_str(&timestamp.to_string())?);
         headers.insert("X-BAPI-RECV-WINDOW", HeaderValue::from_str(RECV_WINDOW)?);
         headers.insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -204,7 +735,110 @@ impl Bybit {
         Ok(resp)
     }
 
-    pub async fn cancel_order(&self, category: Category, symbol: &str, order_id: OrderId) -> anyhow::Result<()> {
+    async fn get_all_pages<T: DeserializeOwned>(&self, endpoint: &str, params: Value) -> anyhow::Result<Vec<T>> {
+        self.get_all_pages_of(endpoint, params, "list").await
+    }
+
+    // same as get_all_pages for endpoints returning their items under `list_key`
+    async fn get_all_pages_of<T: DeserializeOwned>(&self, endpoint: &str, params: Value, list_key: &str) -> anyhow::Result<Vec<T>> {
+        let mut items: Vec<T> = vec![];
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut page_params = params.clone();
+
+            if let Some(ref c) = cursor {
+                page_params["cursor"] = json!(c);
+            }
+
+            let resp = self.get_request(endpoint, page_params).await?;
+            let txt = resp.text().await?;
+            let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+            if resp.ret_code != 0 {
+                bail!("bybit err resp: {}", resp.ret_msg);
+            }
+
+            let list = resp.result
+                .get(list_key)
+                .and_then(Value::as_array)
+                .with_context(|| format!("Failed to extract {list_key} from response"))?;
+
+            for item in list.iter() {
+                items.push(serde_json::from_value(item.clone())?);
+            }
+
+            let next_cursor = resp.result
+                .get("nextPageCursor")
+                .and_then(Value::as_str)
+                .unwrap_or("");
+
+            if next_cursor.is_empty() || list.is_empty() {
+                break;
+            }
+            cursor = Some(next_cursor.to_string());
+        }
+
+        Ok(items)
+    }
+
+    async fn get_all_pages_in_range<T: DeserializeOwned>(&self, endpoint: &str, params: Value, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<T>> {
+        self.get_all_pages_in_windows(endpoint, params, "list", QUERY_WINDOW_MS, start_time, end_time).await
+    }
+
+    async fn get_all_pages_in_windows<T: DeserializeOwned>(&self, endpoint: &str, mut params: Value, list_key: &str, window_ms: u64, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<T>> {
+        let start = match start_time {
+            Some(start) => start,
+            None => {
+                // bybit defaults to the window before endTime (or now)
+                if let Some(end) = end_time {
+                    params["endTime"] = json!(end.to_string());
+                }
+                return self.get_all_pages_of(endpoint, params, list_key).await;
+            }
+        };
+
+        let end = end_time.unwrap_or(get_timestamp() as u64);
+        ensure!(start <= end, "start_time is after end_time");
+
+        let mut items: Vec<T> = vec![];
+
+        for (from, to) in split_time_range(start, end, window_ms) {
+            let mut window_params = params.clone();
+            window_params["startTime"] = json!(from.to_string());
+            window_params["endTime"] = json!(to.to_string());
+
+            let mut page = self.get_all_pages_of(endpoint, window_params, list_key).await?;
+            items.append(&mut page);
+        }
+
+        Ok(items)
+    }
+
+    async fn signed_post(&self, endpoint: &str, params: Value) -> anyhow::Result<BybitResponse> {
+        let raw_request_body = params.to_string();
+
+        let timestamp = get_timestamp();
+
+        let signature = self.make_signature(timestamp, &raw_request_body)?;
+        let resp = self.post_request(endpoint, timestamp, &signature, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+        Ok(resp)
+    }
+
+    async fn signed_get(&self, endpoint: &str, params: Value) -> anyhow::Result<BybitResponse> {
+        let resp = self.get_request(endpoint, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+        Ok(resp)
+    }
+
+    pub async fn cancel_order(&self, category: Category, symbol: &str, order_id: OrderId, order_filter: Option<OrderFilter>) -> anyhow::Result<()> {
         let endpoint = "/v5/order/cancel";
 
         let mut params = json!({
@@ -221,6 +855,11 @@ impl Bybit {
             }
         }
 
+        // only spot needs the filter to find conditional orders
+        if let Some(filter) = order_filter {
+            params["orderFilter"] = json!(filter);
+        }
+
         let raw_request_body = params.to_string();
         //println!("{raw_request_body}");
 
@@ -242,14 +881,44 @@ impl Bybit {
         Ok(())
     }
 
-    pub async fn cancel_all_orders(&self, category: Category, symbol: &str) -> anyhow::Result<()> {
+    /// Cancels every order matching the scope and returns the cancelled ids. Linear and inverse
+    /// need a scope; `stop_order_type` narrows a `StopOrder` filter further.
+    pub async fn cancel_all_orders(&self, category: Category, scope_op: Option<OrderScope>, order_filter: Option<OrderFilter>, stop_order_type: Option<StopOrderType>) -> anyhow::Result<Vec<CancelledOrder>> {
         let endpoint = "/v5/order/cancel-all";
 
-        let params = json!({
+        if category.eq(&Category::Linear) || category.eq(&Category::Inverse) {
+            ensure!(scope_op.is_some(), "cancel_all_orders() needs a symbol, baseCoin or settleCoin for {category:?}");
+        }
+
+        if stop_order_type.is_some() {
+            ensure!(order_filter.eq(&Some(OrderFilter::StopOrder)), "stop_order_type requires the StopOrder filter");
+        }
+
+        let mut params = json!({
             "category": category,
-            "symbol": symbol,
         });
 
+        match scope_op {
+            Some(OrderScope::Symbol(symbol)) => {
+                params["symbol"] = json!(symbol);
+            },
+            Some(OrderScope::BaseCoin(coin)) => {
+                params["baseCoin"] = json!(coin);
+            },
+            Some(OrderScope::SettleCoin(coin)) => {
+                params["settleCoin"] = json!(coin);
+            },
+            None => {}
+        }
+
+        if let Some(filter) = order_filter {
+            params["orderFilter"] = json!(filter);
+        }
+
+        if let Some(stop_type) = stop_order_type {
+            params["stopOrderType"] = json!(stop_type);
+        }
+
         let raw_request_body = params.to_string();
         //println!("{raw_request_body}");
 
@@ -269,32 +938,22 @@ impl Bybit {
             bail!("bybit err resp: {}", resp.ret_msg);
         }
 
-        Ok(())
-    }
-
-    pub async fn create_order(&self, category: Category, symbol: &str, side: TradeDirection, order_type: OrderType, q: f64, price: Option<f64>, time_in_force: Option<TimeInForce>) -> anyhow::Result<CreateOrderResponse> {
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract cancelled list from response")?;
 
-        let endpoint = "/v5/order/create";
+        let cancelled: Vec<CancelledOrder> = serde_json::from_value(list)?;
 
-        if order_type.eq(&OrderType::Limit) {
-            ensure!(price.is_some(), "create_order() missing price");
-        }
+        Ok(cancelled)
+    }
 
-        let mut params = json!({
-            "category": category,
-            "symbol": symbol,
-            "side": side,
-            "qty": q.to_string(),
-            "orderType": order_type,
-        });
+    pub async fn create_order(&self, category: Category, order: OrderRequest) -> anyhow::Result<CreateOrderResponse> {
 
-        if let Some(p) = price {
-            params["price"] = json!(p.to_string());
-        }
+        let endpoint = "/v5/order/create";
 
-        if let Some(tip) = time_in_force {
-            params["timeInForce"] = json!(tip);
-        }
+        let mut params = order.into_json(category)?;
+        params["category"] = json!(category);
 
         let raw_request_body = params.to_string();
         //println!("{raw_request_body}");
@@ -320,7 +979,7 @@ impl Bybit {
        Ok(order_query)
     }
 
-    pub async fn batch_create_order(&self, category: Category, new_orders: Vec<NewOrder>) -> anyhow::Result<CreateBatchOrderResponse> {
+    pub async fn batch_create_order(&self, category: Category, new_orders: Vec<OrderRequest>) -> anyhow::Result<CreateBatchOrderResponse> {
 
         let endpoint = "/v5/order/create-batch";
 
@@ -328,8 +987,8 @@ impl Bybit {
 
         let mut orders: Vec<serde_json::Value> = vec![];
 
-        for order in new_orders.into_iter() {
-            let json = order.into_json()?;
+        for order in new_orders.iter() {
+            let json = order.into_json(category)?;
             orders.push(json);
         }
 
@@ -362,62 +1021,293 @@ impl Bybit {
        Ok(order_receipts)
     }
 
-    pub async fn get_orders(&self, category: Category, symbol: &str, order_id_op: Option<OrderId>) -> anyhow::Result<Vec<Order>> {
-        let endpoint = "/v5/order/realtime";
-
-        let mut params = json!({
-            "category": category,
-            "symbol": symbol,
-        });
+    pub async fn set_trading_stop(&self, category: Category, request: TradingStopRequest) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/trading-stop";
 
-        if let Some(order_id) = order_id_op {
-            match order_id {
-                OrderId::OrderID(id) => {
-                    params["orderId"] = json!(id);
-                },
-                OrderId::OrderLinkID(id) => {
-                    params["orderLinkId"] = json!(id);
-                }
-            }
-        }
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "trading stop is only available for linear and inverse");
 
-        let resp = self.get_request(endpoint, params).await?;
-        let txt = resp.text().await?;
-        //println!("resp: {txt}");
+        let mut params = request.into_json()?;
+        params["category"] = json!(category);
 
-        let resp: BybitResponse = serde_json::from_str(&txt)?;
+        let resp = self.signed_post(endpoint, params).await?;
 
         if resp.ret_code != 0 {
             bail!("bybit err resp: {}", resp.ret_msg);
         }
 
-        let list = resp.result.get("list").ok_or_else(|| anyhow!("No list field"))?;
-        let order_list = list.as_array().ok_or_else(|| anyhow!("No order list"))?;
+        Ok(())
+    }
 
-        //dbg!(&order_list);
+    pub async fn set_leverage(&self, category: Category, symbol: &str, buy_leverage: f64, sell_leverage: f64) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/set-leverage";
 
-        let mut orders = vec![];
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "leverage is only available for linear and inverse");
 
-        for order in order_list.iter() {
-            let o: Order = serde_json::from_value(order.clone())?;
-            orders.push(o);
+        self.check_leverage(category, symbol, &[buy_leverage, sell_leverage]).await?;
+
+        let params = json!({
+            "category": category,
+            "symbol": symbol,
+            "buyLeverage": buy_leverage.to_string(),
+            "sellLeverage": sell_leverage.to_string(),
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 && resp.ret_code != LEVERAGE_NOT_MODIFIED {
+            bail!("bybit err resp: {}", resp.ret_msg);
         }
 
-        Ok(orders)
+        Ok(())
     }
 
-    pub async fn get_wallet_balance(&self, account_type: AccountType,symbol_op: Option<&str>) -> anyhow::Result<AccountInfo> {
+    async fn check_leverage(&self, category: Category, symbol: &str, leverages: &[f64]) -> anyhow::Result<()> {
+        let contracts = self.get_instrument_info(category, Some(symbol)).await?;
+        let contract = contracts.get(symbol).with_context(|| format!("unknown symbol {symbol}"))?;
 
-        let endpoint = "/v5/account/wallet-balance";
+        for leverage in leverages {
+            contract.leverage_filter.check(*leverage)?;
+        }
+
+        Ok(())
+    }
+
+    /// Switches between one-way and hedge mode, for a single symbol or every symbol settled in `coin`.
+    pub async fn switch_position_mode(&self, category: Category, symbol_op: Option<&str>, coin_op: Option<&str>, mode: PositionMode) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/switch-mode";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "position mode is only available for linear and inverse");
+        ensure!(symbol_op.is_some() || coin_op.is_some(), "switch_position_mode() needs a symbol or coin");
 
         let mut params = json!({
-            "accountType": account_type,
+            "category": category,
+            "mode": mode as i32,
         });
 
         if let Some(symbol) = symbol_op {
             params["symbol"] = json!(symbol);
         }
 
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 && resp.ret_code != POSITION_MODE_NOT_MODIFIED {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn switch_isolated(&self, category: Category, symbol: &str, trade_mode: TradeMode, buy_leverage: f64, sell_leverage: f64) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/switch-isolated";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "margin mode is only available for linear and inverse");
+
+        self.check_leverage(category, symbol, &[buy_leverage, sell_leverage]).await?;
+
+        let params = json!({
+            "category": category,
+            "symbol": symbol,
+            "tradeMode": trade_mode as i32,
+            "buyLeverage": buy_leverage.to_string(),
+            "sellLeverage": sell_leverage.to_string(),
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 && resp.ret_code != MARGIN_MODE_NOT_MODIFIED {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Adds (positive) or reduces (negative) margin on an isolated position.
+    pub async fn add_margin(&self, category: Category, symbol: &str, margin: f64, position_idx: Option<PositionIdx>) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/add-margin";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "margin is only available for linear and inverse");
+        ensure!(margin != 0.0, "margin must not be zero");
+
+        let mut params = json!({
+            "category": category,
+            "symbol": symbol,
+            "margin": margin.to_string(),
+        });
+
+        if let Some(idx) = position_idx {
+            params["positionIdx"] = json!(idx);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_auto_add_margin(&self, category: Category, symbol: &str, auto_add_margin: bool, position_idx: Option<PositionIdx>) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/set-auto-add-margin";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "auto add margin is only available for linear and inverse");
+
+        let mut params = json!({
+            "category": category,
+            "symbol": symbol,
+            "autoAddMargin": auto_add_margin as i32,
+        });
+
+        if let Some(idx) = position_idx {
+            params["positionIdx"] = json!(idx);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_risk_limit(&self, category: Category, symbol: &str, risk_id: i32, position_idx: Option<PositionIdx>) -> anyhow::Result<()> {
+        let endpoint = "/v5/position/set-risk-limit";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "risk limit is only available for linear and inverse");
+
+        let mut params = json!({
+            "category": category,
+            "symbol": symbol,
+            "riskId": risk_id,
+        });
+
+        if let Some(idx) = position_idx {
+            params["positionIdx"] = json!(idx);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 && resp.ret_code != RISK_ID_NOT_MODIFIED {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the disconnect cancel-all window of `product` to `time_window` seconds (3-300).
+    /// Bybit only acts on it when a private websocket subscribed to the `dcp` topic drops:
+    /// orders are cancelled if it doesn't reconnect within the window. Use `start_dcp_keepalive`
+    /// to hold such a connection.
+    pub async fn set_dcp_window(&self, product: DcpProduct, time_window: u64) -> anyhow::Result<()> {
+        let endpoint = "/v5/order/disconnected-cancel-all";
+
+        ensure!((3..=300).contains(&time_window), "time_window must be 3-300 seconds");
+
+        let params = json!({
+            "product": product,
+            "timeWindow": time_window,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the DCP window of `products` and spawns a task holding a private websocket
+    /// subscribed to their `dcp` topics. Once that connection has been gone for `time_window`
+    /// seconds bybit cancels the orders, so they don't outlive a crashed process. The task
+    /// reconnects by itself and runs until `stop_dcp_keepalive` or until this client is
+    /// dropped. Replaces any running keepalive.
+    pub async fn start_dcp_keepalive(&self, products: &[DcpProduct], time_window: u64) -> anyhow::Result<()> {
+        ensure!(!products.is_empty(), "products is empty");
+
+        let api_key = self.api_key.clone().ok_or_else(|| anyhow!("Missing api key"))?;
+        let api_secret = self.api_secret.clone().ok_or_else(|| anyhow!("Missing secret key"))?;
+
+        for product in products {
+            self.set_dcp_window(*product, time_window).await?;
+        }
+
+        let topics: Vec<&'static str> = products.iter().map(|product| product.ws_topic()).collect();
+
+        // the first session is opened here so bad keys fail this call rather than the task
+        let mut socket = open_dcp_session(&api_key, &api_secret, &topics).await?;
+
+        let handle = tokio::spawn(async move {
+            loop {
+                // the session only ends on a dropped connection, reconnect before the window lapses
+                let _ = keep_dcp_session(&mut socket).await;
+
+                loop {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+
+                    if let Ok(reconnected) = open_dcp_session(&api_key, &api_secret, &topics).await {
+                        socket = reconnected;
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut task = self.dcp_keepalive.lock().map_err(|_| anyhow!("dcp keepalive lock poisoned"))?;
+
+        if let Some(previous) = task.replace(handle) {
+            previous.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Closes the dcp websocket; bybit cancels the orders once the window lapses.
+    pub fn stop_dcp_keepalive(&self) -> anyhow::Result<()> {
+        let mut task = self.dcp_keepalive.lock().map_err(|_| anyhow!("dcp keepalive lock poisoned"))?;
+
+        if let Some(handle) = task.take() {
+            handle.abort();
+        }
+
+        Ok(())
+    }
+
+    pub fn dcp_keepalive_running(&self) -> bool {
+        self.dcp_keepalive
+            .lock()
+            .map(|task| task.as_ref().is_some_and(|handle| !handle.is_finished()))
+            .unwrap_or(false)
+    }
+
+    pub async fn get_orders(&self, category: Category, symbol: &str, order_id_op: Option<OrderId>, order_filter: Option<OrderFilter>) -> anyhow::Result<Vec<Order>> {
+        let endpoint = "/v5/order/realtime";
+
+        let mut params = json!({
+            "category": category,
+            "symbol": symbol,
+        });
+
+        if let Some(order_id) = order_id_op {
+            match order_id {
+                OrderId::OrderID(id) => {
+                    params["orderId"] = json!(id);
+                },
+                OrderId::OrderLinkID(id) => {
+                    params["orderLinkId"] = json!(id);
+                }
+            }
+        }
+
+        if let Some(filter) = order_filter {
+            params["orderFilter"] = json!(filter);
+        }
+
         let resp = self.get_request(endpoint, params).await?;
         let txt = resp.text().await?;
         //println!("resp: {txt}");
@@ -428,32 +1318,149 @@ impl Bybit {
             bail!("bybit err resp: {}", resp.ret_msg);
         }
 
-        //dbg!(&resp.result);
+        let list = resp.result.get("list").ok_or_else(|| anyhow!("No list field"))?;
+        let order_list = list.as_array().ok_or_else(|| anyhow!("No order list"))?;
 
-        let account_obj = resp.result
-        .get("list")
-        .and_then(Value::as_array)
-        .and_then(|arr| arr.first())
-        .context("Failed to extract account info from response")?;
+        //dbg!(&order_list);
 
-        let account_info: AccountInfo = serde_json::from_value(account_obj.clone())?;
+        let mut orders = vec![];
+
+        for order in order_list.iter() {
+            let o: Order = serde_json::from_value(order.clone())?;
+            orders.push(o);
+        }
 
-        Ok(account_info)
+        Ok(orders)
     }
 
-    pub async fn get_instrument_info(&self, category: Category,symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, ContractInfo>> {
+    pub async fn get_executions(&self, category: Category, symbol_op: Option<&str>, order_id_op: Option<OrderId>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<Execution>> {
+        let endpoint = "/v5/execution/list";
 
-        let endpoint = "/v5/market/instruments-info";
+        let mut params = json!({
+            "category": category,
+            "limit": "100",
+        });
+
+        if let Some(symbol) = symbol_op {
+            params["symbol"] = json!(symbol);
+        }
+
+        if let Some(order_id) = order_id_op {
+            match order_id {
+                OrderId::OrderID(id) => {
+                    params["orderId"] = json!(id);
+                },
+                OrderId::OrderLinkID(id) => {
+                    params["orderLinkId"] = json!(id);
+                }
+            }
+        }
+
+        self.get_all_pages_in_range(endpoint, params, start_time, end_time).await
+    }
+
+    pub async fn get_closed_pnl(&self, category: Category, symbol_op: Option<&str>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<ClosedPnl>> {
+        let endpoint = "/v5/position/closed-pnl";
+
+        ensure!(category.eq(&Category::Linear) || category.eq(&Category::Inverse), "closed pnl is only available for linear and inverse");
 
         let mut params = json!({
             "category": category,
+            "limit": "100",
         });
 
         if let Some(symbol) = symbol_op {
             params["symbol"] = json!(symbol);
         }
 
-        let resp = self.get_request_no_sign(endpoint, params).await?;
+        self.get_all_pages_in_range(endpoint, params, start_time, end_time).await
+    }
+
+    /// Every order in `category` matching `scope_op` and `listing`, following all pages.
+    /// Without a scope, linear is queried for both USDT and USDC settlement.
+    pub async fn get_open_orders(&self, category: Category, scope_op: Option<OrderScope>, listing: OrderListing, order_filter: Option<OrderFilter>) -> anyhow::Result<Vec<Order>> {
+        let endpoint = "/v5/order/realtime";
+
+        let scopes = match scope_op {
+            Some(scope) => vec![Some(scope)],
+            None if category.eq(&Category::Linear) => vec![
+                Some(OrderScope::SettleCoin(String::from("USDT"))),
+                Some(OrderScope::SettleCoin(String::from("USDC"))),
+            ],
+            None => vec![None],
+        };
+
+        let mut orders = vec![];
+
+        for scope in scopes {
+            let mut params = json!({
+                "category": category,
+                "openOnly": listing,
+                "limit": "50",
+            });
+
+            match scope {
+                Some(OrderScope::Symbol(symbol)) => {
+                    params["symbol"] = json!(symbol);
+                },
+                Some(OrderScope::BaseCoin(coin)) => {
+                    params["baseCoin"] = json!(coin);
+                },
+                Some(OrderScope::SettleCoin(coin)) => {
+                    params["settleCoin"] = json!(coin);
+                },
+                None => {}
+            }
+
+            if let Some(filter) = order_filter {
+                params["orderFilter"] = json!(filter);
+            }
+
+            let mut page: Vec<Order> = self.get_all_pages(endpoint, params).await?;
+            orders.append(&mut page);
+        }
+
+        Ok(orders)
+    }
+
+    /// Snapshot of every open order across spot, linear (USDT and USDC), inverse and option.
+    pub async fn get_all_open_orders(&self) -> anyhow::Result<Vec<(Category, Order)>> {
+        let mut orders = vec![];
+
+        for category in [Category::Spot, Category::Linear, Category::Inverse, Category::Option] {
+            let list = self.get_open_orders(category, None, OrderListing::OpenOrders, None).await?;
+            orders.extend(list.into_iter().map(|order| (category, order)));
+        }
+
+        Ok(orders)
+    }
+
+    pub async fn get_wallet_balance(&self, account_type: AccountType, coin_op: Option<&str>) -> anyhow::Result<AccountInfo> {
+        self.get_wallet_balances(account_type, coin_op).await?
+            .into_iter()
+            .next()
+            .context("Failed to extract account info from response")
+    }
+
+    /// One entry per wallet of `account_type` (UNIFIED, or CONTRACT/SPOT on classic accounts).
+    pub async fn get_wallet_balances(&self, account_type: AccountType, coin_op: Option<&str>) -> anyhow::Result<Vec<AccountInfo>> {
+
+        let endpoint = "/v5/account/wallet-balance";
+
+        ensure!(
+            matches!(account_type, AccountType::UNIFIED | AccountType::CONTRACT | AccountType::SPOT),
+            "wallet balance is only available for UNIFIED, CONTRACT and SPOT, use get_coins_balance"
+        );
+
+        let mut params = json!({
+            "accountType": account_type,
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        let resp = self.get_request(endpoint, params).await?;
         let txt = resp.text().await?;
         //println!("resp: {txt}");
 
@@ -465,314 +1472,2036 @@ impl Bybit {
 
         //dbg!(&resp.result);
 
-        let contract_list = resp.result
+        let account_list = resp.result
         .get("list")
-        .and_then(Value::as_array)
-        .context("Failed to extract contract list from response")?;
+        .cloned()
+        .context("Failed to extract account info from response")?;
 
-        let mut map: HashMap<String, ContractInfo> = HashMap::default();
+        let accounts: Vec<AccountInfo> = serde_json::from_value(account_list)?;
+
+        Ok(accounts)
+    }
+
+    /// Balance of every coin in any wallet, including FUND and INVESTMENT. UNIFIED needs `coin_op`
+    /// (up to 10 comma-separated coins); use `get_wallet_balance` for all of its coins.
+    pub async fn get_coins_balance(&self, account_type: AccountType, coin_op: Option<&str>) -> anyhow::Result<Vec<CoinBalance>> {
+        let endpoint = "/v5/asset/transfer/query-account-coins-balance";
+
+        ensure!(
+            account_type != AccountType::UNIFIED || coin_op.is_some(),
+            "coin is required for UNIFIED, use get_wallet_balance for every coin"
+        );
+
+        let mut params = json!({
+            "accountType": account_type,
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let balances = resp.result
+            .get("balance")
+            .cloned()
+            .context("Failed to extract balance from response")?;
+
+        let balances: Vec<CoinBalance> = serde_json::from_value(balances)?;
+        Ok(balances)
+    }
+
+    /// Wallet balances of `account_types` merged per coin, e.g. `[UNIFIED, FUND]` for a unified
+    /// account or `[CONTRACT, SPOT, FUND]` for a classic one.
+    pub async fn get_total_holdings(&self, account_types: &[AccountType]) -> anyhow::Result<HashMap<String, CoinHolding>> {
+        let mut holdings: HashMap<String, CoinHolding> = HashMap::default();
+
+        for account_type in account_types {
+            // all-coins balance needs an explicit coin list for UNIFIED, wallet balance doesn't
+            let balances: Vec<(String, f64)> = if *account_type == AccountType::UNIFIED {
+                self.get_wallet_balance(*account_type, None).await?
+                    .coin
+                    .into_iter()
+                    .map(|c| (c.coin, c.wallet_balance))
+                    .collect()
+            } else {
+                self.get_coins_balance(*account_type, None).await?
+                    .into_iter()
+                    .map(|b| (b.coin, b.wallet_balance))
+                    .collect()
+            };
+
+            for (coin, wallet_balance) in balances {
+                if wallet_balance == 0.0 {
+                    continue;
+                }
+
+                holdings
+                    .entry(coin.clone())
+                    .or_insert_with(|| CoinHolding::new(&coin))
+                    .add(*account_type, wallet_balance);
+            }
+        }
+
+        Ok(holdings)
+    }
+
+    pub async fn get_instrument_info(&self, category: Category,symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, ContractInfo>> {
+
+        let endpoint = "/v5/market/instruments-info";
+
+        let mut params = json!({
+            "category": category,
+        });
+
+        if let Some(symbol) = symbol_op {
+            params["symbol"] = json!(symbol);
+        }
+
+        let resp = self.get_request_no_sign(endpoint, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        //dbg!(&resp.result);
+
+        let contract_list = resp.result
+        .get("list")
+        .and_then(Value::as_array)
+        .context("Failed to extract contract list from response")?;
+
+        let mut map: HashMap<String, ContractInfo> = HashMap::default();
+
+        for contract in contract_list.iter() {
+            let info: ContractInfo = serde_json::from_value(contract.clone())?;
+            map.insert(info.symbol.clone(), info);
+        }
+
+        Ok(map)
+    }
+
+    pub async fn get_instrument_info_all(&self, category: Category) -> anyhow::Result<HashMap<String, ContractInfo>> {
+        let endpoint = "/v5/market/instruments-info";
+        let mut map: HashMap<String, ContractInfo> = HashMap::default();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut params = json!({
+                "category": category,
+                "limit": "1000",
+            });
+
+            if let Some(ref c) = cursor {
+                params["cursor"] = json!(c);
+            }
+
+            let resp = self.get_request_no_sign(endpoint, params).await?;
+            let txt = resp.text().await?;
+            let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+            if resp.ret_code != 0 {
+                bail!("bybit err resp: {}", resp.ret_msg);
+            }
+
+            let contract_list = resp.result
+                .get("list")
+                .and_then(Value::as_array)
+                .context("Failed to extract contract list from response")?;
+
+            for contract in contract_list.iter() {
+                let info: ContractInfo = serde_json::from_value(contract.clone())?;
+                map.insert(info.symbol.clone(), info);
+            }
+
+            let next_cursor = resp.result
+                .get("nextPageCursor")
+                .and_then(Value::as_str)
+                .unwrap_or("");
+
+            if next_cursor.is_empty() {
+                break;
+            }
+            cursor = Some(next_cursor.to_string());
+        }
+
+        Ok(map)
+    }
+
+    pub async fn get_futures_tickers(&self, symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, TickerData>> {
+
+        let endpoint = "/v5/market/tickers";
+
+        let mut params = json!({
+            "category": Category::Linear,
+        });
+
+        if let Some(symbol) = symbol_op {
+            params["symbol"] = json!(symbol);
+        }
+
+        let resp = self.get_request_no_sign(endpoint, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        //std::fs::write("ftickersraw.json", &txt).unwrap();
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        //dbg!(&resp.result);
+
+        let contract_list = resp.result
+        .get("list")
+        .and_then(Value::as_array)
+        .context("Failed to extract contract list from response")?;
+
+        let mut map: HashMap<String, TickerData> = HashMap::default();
+
+        for contract in contract_list.iter() {
+            let info: TickerData = serde_json::from_value(contract.clone())?;
+            map.insert(info.symbol.clone(), info);
+        }
+
+        Ok(map)
+    }
+
+    pub async fn get_spot_tickers(&self, symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, SpotTickerData>> {
+
+        let endpoint = "/v5/market/tickers";
+
+        let mut params = json!({
+            "category": Category::Spot,
+        });
+
+        if let Some(symbol) = symbol_op {
+            params["symbol"] = json!(symbol);
+        }
+
+        let resp = self.get_request(endpoint, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        //dbg!(&resp.result);
+
+        let contract_list = resp.result
+        .get("list")
+        .and_then(Value::as_array)
+        .context("Failed to extract contract list from response")?;
+
+        let mut map: HashMap<String, SpotTickerData> = HashMap::default();
+
+        for contract in contract_list.iter() {
+            let info: SpotTickerData = serde_json::from_value(contract.clone())?;
+            map.insert(info.symbol.clone(), info);
+        }
+
+        Ok(map)
+    }
+
+    pub async fn get_options_tickers(&self, base_coin: &str) -> anyhow::Result<Vec<OptionTickerData>> {
+
+        let endpoint = "/v5/market/tickers";
+
+        let params = json!({
+            "category": Category::Option,
+            "baseCoin": base_coin
+        });
+
+        let resp = self.get_request(endpoint, params).await?;
+        let txt = resp.text().await?;
+        //println!("resp: {txt}");
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list_value = resp
+        .result
+        .get("list")
+        .cloned()                      // turn &Value into Value
+        .context("failed to extract contract list from response")?;
+
+        let data: Vec<OptionTickerData> = serde_json::from_value(list_value)?;
+        Ok(data)
+    }
+
+    /// Positions keyed by `(symbol, positionIdx)`, so both legs of a hedge-mode symbol are kept.
+    /// Follows every page. Without a scope, linear is queried for both USDT and USDC settlement.
+    pub async fn get_position_info(&self, category: Category, scope_op: Option<PositionScope>) -> anyhow::Result<HashMap<(String, PositionIdx), PositionInfo>> {
+
+        let endpoint = "/v5/position/list";
+
+        let scopes = match scope_op {
+            Some(scope) => vec![Some(scope)],
+            None if category.eq(&Category::Linear) => vec![
+                Some(PositionScope::SettleCoin(String::from("USDT"))),
+                Some(PositionScope::SettleCoin(String::from("USDC"))),
+            ],
+            None => vec![None],
+        };
+
+        let mut map: HashMap<(String, PositionIdx), PositionInfo> = HashMap::default();
+
+        for scope in scopes {
+            let mut params = json!({
+                "category": category,
+                "limit": "200",
+            });
+
+            match scope {
+                Some(PositionScope::Symbol(symbol)) => {
+                    params["symbol"] = json!(symbol);
+                },
+                Some(PositionScope::BaseCoin(coin)) => {
+                    ensure!(category.eq(&Category::Option), "baseCoin filter is only available for option");
+                    params["baseCoin"] = json!(coin);
+                },
+                Some(PositionScope::SettleCoin(coin)) => {
+                    params["settleCoin"] = json!(coin);
+                },
+                None => {}
+            }
+
+            let positions: Vec<PositionInfo> = self.get_all_pages(endpoint, params).await?;
+
+            for info in positions {
+                map.insert((info.symbol.clone(), info.position_idx), info);
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Every position across linear (USDT and USDC), inverse and option.
+    pub async fn get_all_positions(&self) -> anyhow::Result<Vec<(Category, PositionInfo)>> {
+        let mut positions = vec![];
+
+        for category in [Category::Linear, Category::Inverse, Category::Option] {
+            let map = self.get_position_info(category, None).await?;
+            positions.extend(map.into_values().map(|info| (category, info)));
+        }
+
+        Ok(positions)
+    }
+
+    pub async fn set_spot_margin_mode(&self, enabled: bool) -> anyhow::Result<()> {
+        let endpoint = "/v5/spot-margin-trade/switch-mode";
+
+        let params = json!({
+            "spotMarginMode": if enabled { "1" } else { "0" },
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_spot_margin_leverage(&self, leverage: f64) -> anyhow::Result<()> {
+        let endpoint = "/v5/spot-margin-trade/set-leverage";
+
+        ensure!((2.0..=10.0).contains(&leverage), "spot margin leverage must be 2-10");
+
+        let params = json!({
+            "leverage": leverage.to_string(),
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_spot_margin_state(&self) -> anyhow::Result<SpotMarginState> {
+        let endpoint = "/v5/spot-margin-trade/state";
+
+        let resp = self.signed_get(endpoint, json!({})).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let state: SpotMarginState = serde_json::from_value(resp.result)?;
+        Ok(state)
+    }
+
+    /// Borrowable coins, collateral ratios and borrow rates per VIP level.
+    pub async fn get_spot_margin_data(&self, vip_level_op: Option<&str>, currency_op: Option<&str>) -> anyhow::Result<Vec<VipMarginData>> {
+        let endpoint = "/v5/spot-margin-trade/data";
+
+        let mut params = json!({});
+
+        if let Some(vip_level) = vip_level_op {
+            params["vipLevel"] = json!(vip_level);
+        }
+
+        if let Some(currency) = currency_op {
+            params["currency"] = json!(currency);
+        }
+
+        let resp = self.get_request_no_sign(endpoint, params).await?;
+        let txt = resp.text().await?;
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("vipCoinList")
+            .cloned()
+            .context("Failed to extract vipCoinList from response")?;
+
+        let data: Vec<VipMarginData> = serde_json::from_value(list)?;
+        Ok(data)
+    }
+
+    /// Tiered collateral ratios per coin.
+    pub async fn get_spot_margin_collateral(&self, currency_op: Option<&str>) -> anyhow::Result<Vec<CollateralRatioInfo>> {
+        let endpoint = "/v5/spot-margin-trade/collateral";
+
+        let mut params = json!({});
+
+        if let Some(currency) = currency_op {
+            params["currency"] = json!(currency);
+        }
+
+        let resp = self.get_request_no_sign(endpoint, params).await?;
+        let txt = resp.text().await?;
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract collateral list from response")?;
+
+        let data: Vec<CollateralRatioInfo> = serde_json::from_value(list)?;
+        Ok(data)
+    }
+
+    /// Hourly borrow rates for `currency`, bybit caps the range at 30 days.
+    pub async fn get_spot_margin_interest_rate_history(&self, currency: &str, vip_level_op: Option<&str>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<InterestRateRecord>> {
+        let endpoint = "/v5/spot-margin-trade/interest-rate-history";
+
+        let mut params = json!({
+            "currency": currency,
+        });
+
+        if let Some(vip_level) = vip_level_op {
+            params["vipLevel"] = json!(vip_level);
+        }
+
+        if let Some(start) = start_time {
+            params["startTime"] = json!(start.to_string());
+        }
+
+        if let Some(end) = end_time {
+            params["endTime"] = json!(end.to_string());
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract interest rate list from response")?;
+
+        let records: Vec<InterestRateRecord> = serde_json::from_value(list)?;
+        Ok(records)
+    }
+
+    /// Fetches the account's fee rates and refreshes the cache. Without a symbol, spot and linear
+    /// return every symbol; options are keyed by `base_coin_op` instead.
+    pub async fn get_fee_rates(&self, category: Category, symbol_op: Option<&str>, base_coin_op: Option<&str>) -> anyhow::Result<Vec<FeeRate>> {
+        let endpoint = "/v5/account/fee-rate";
+
+        let mut params = json!({
+            "category": category,
+        });
+
+        if let Some(symbol) = symbol_op {
+            params["symbol"] = json!(symbol);
+        }
+
+        if let Some(base_coin) = base_coin_op {
+            ensure!(category.eq(&Category::Option), "baseCoin filter is only available for option");
+            params["baseCoin"] = json!(base_coin);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract fee rate list from response")?;
+
+        let rates: Vec<FeeRate> = serde_json::from_value(list)?;
+
+        let mut cache = self.fee_rates.lock().map_err(|_| anyhow!("fee rate cache lock poisoned"))?;
+
+        for rate in rates.iter() {
+            cache.insert((category, rate.key().to_string()), rate.clone());
+        }
+
+        Ok(rates)
+    }
+
+    pub fn cached_fee_rate(&self, category: Category, symbol: &str) -> Option<FeeRate> {
+        let key = fee_rate_key(category, symbol);
+        self.fee_rates.lock().ok()?.get(&(category, key.to_string())).cloned()
+    }
+
+    /// Fee rate for `symbol`, fetched on the first call and served from the cache afterwards.
+    pub async fn fee_rate(&self, category: Category, symbol: &str) -> anyhow::Result<FeeRate> {
+        if let Some(rate) = self.cached_fee_rate(category, symbol) {
+            return Ok(rate);
+        }
+
+        if category.eq(&Category::Option) {
+            self.get_fee_rates(category, None, Some(fee_rate_key(category, symbol))).await?;
+        } else {
+            self.get_fee_rates(category, Some(symbol), None).await?;
+        }
+
+        self.cached_fee_rate(category, symbol).with_context(|| format!("no fee rate for {symbol}"))
+    }
+
+    pub fn clear_fee_rate_cache(&self) {
+        if let Ok(mut cache) = self.fee_rates.lock() {
+            cache.clear();
+        }
+    }
+
+    /// Expected notional and fee of `order` using the account's fee rate, for linear, inverse and
+    /// spot (options are not supported). Market orders need a `reference_price`. `maker_op` overrides
+    /// the maker/taker guess, which otherwise only treats PostOnly/RPI orders as maker.
+    pub async fn estimate_order_cost(&self, category: Category, order: &OrderRequest, reference_price: Option<f64>, maker_op: Option<bool>) -> anyhow::Result<OrderCostEstimate> {
+        ensure!(!category.eq(&Category::Option), "cost estimation is not supported for options");
+        let rate = self.fee_rate(category, &order.symbol).await?;
+        OrderCostEstimate::new(category, order, &rate, reference_price, maker_op)
+    }
+
+    /// Unified account transaction log (trades, funding, settlement, transfers...), following every
+    /// page and 7-day window between `start_time` and `end_time`.
+    pub async fn get_transaction_log(&self, category_op: Option<Category>, currency_op: Option<&str>, type_op: Option<TransactionType>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<TransactionLog>> {
+        let endpoint = "/v5/account/transaction-log";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(category) = category_op {
+            params["category"] = json!(category);
+        }
+
+        if let Some(currency) = currency_op {
+            params["currency"] = json!(currency);
+        }
+
+        if let Some(transaction_type) = type_op {
+            ensure!(!transaction_type.eq(&TransactionType::Other), "cannot filter on TransactionType::Other");
+            params["type"] = json!(transaction_type);
+        }
+
+        self.get_all_pages_in_range(endpoint, params, start_time, end_time).await
+    }
+
+    pub async fn get_account_info(&self) -> anyhow::Result<AccountSettings> {
+        let endpoint = "/v5/account/info";
+
+        let resp = self.signed_get(endpoint, json!({})).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let settings: AccountSettings = serde_json::from_value(resp.result)?;
+        Ok(settings)
+    }
+
+    pub async fn set_margin_mode(&self, margin_mode: MarginMode) -> anyhow::Result<()> {
+        let endpoint = "/v5/account/set-margin-mode";
+
+        let params = json!({
+            "setMarginMode": margin_mode,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            // bybit explains a refused switch (open orders, insufficient equity...) in result.reasons
+            let reasons: Vec<String> = resp.result
+                .get("reasons")
+                .and_then(Value::as_array)
+                .map(|list| list.iter().filter_map(|r| r.get("reasonMsg").and_then(Value::as_str)).map(String::from).collect())
+                .unwrap_or_default();
+
+            bail!("bybit err resp: {} {:?}", resp.ret_msg, reasons);
+        }
+
+        Ok(())
+    }
+
+    /// Turns `coin` on or off as collateral for the unified account.
+    pub async fn set_collateral_switch(&self, coin: &str, enabled: bool) -> anyhow::Result<()> {
+        let endpoint = "/v5/account/set-collateral-switch";
+
+        let params = json!({
+            "coin": coin,
+            "collateralSwitch": if enabled { "ON" } else { "OFF" },
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Batch version of `set_collateral_switch`, returns the resulting `(coin, enabled)` states.
+    pub async fn set_collateral_switch_batch(&self, switches: &[(&str, bool)]) -> anyhow::Result<Vec<(String, bool)>> {
+        let endpoint = "/v5/account/set-collateral-switch-batch";
+
+        ensure!(!switches.is_empty(), "switches is empty");
+
+        let request: Vec<Value> = switches.iter().map(|(coin, enabled)| json!({
+            "coin": coin,
+            "collateralSwitch": if *enabled { "ON" } else { "OFF" },
+        })).collect();
+
+        let params = json!({
+            "request": request,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .and_then(Value::as_array)
+            .context("Failed to extract collateral switch list from response")?;
+
+        let states = list.iter().filter_map(|item| {
+            let coin = item.get("coin")?.as_str()?.to_string();
+            let enabled = item.get("collateralSwitch")?.as_str()? == "ON";
+            Some((coin, enabled))
+        }).collect();
+
+        Ok(states)
+    }
+
+    pub async fn get_collateral_info(&self, currency_op: Option<&str>) -> anyhow::Result<Vec<CollateralInfo>> {
+        let endpoint = "/v5/account/collateral-info";
+
+        let mut params = json!({});
+
+        if let Some(currency) = currency_op {
+            params["currency"] = json!(currency);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract collateral list from response")?;
+
+        let info: Vec<CollateralInfo> = serde_json::from_value(list)?;
+        Ok(info)
+    }
+
+    /// Starts the classic to unified account upgrade; poll `get_account_info` while it is in PROCESS.
+    pub async fn upgrade_to_uta(&self) -> anyhow::Result<UpgradeStatus> {
+        let endpoint = "/v5/account/upgrade-to-uta";
+
+        let resp = self.signed_post(endpoint, json!({})).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let status: UpgradeStatus = serde_json::from_value(resp.result)?;
+        Ok(status)
+    }
+
+    /// Configures market maker protection for `config.base_coin` options. Only orders sent with
+    /// `OrderRequest::mmp(true)` count towards the limits.
+    pub async fn set_mmp(&self, config: &MmpConfig) -> anyhow::Result<()> {
+        let endpoint = "/v5/account/mmp-modify";
+
+        let params = config.into_json()?;
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Unfreezes MMP for `base_coin` ahead of the frozen period.
+    pub async fn reset_mmp(&self, base_coin: &str) -> anyhow::Result<()> {
+        let endpoint = "/v5/account/mmp-reset";
+
+        let params = json!({
+            "baseCoin": base_coin,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_mmp_state(&self, base_coin: &str) -> anyhow::Result<Vec<MmpState>> {
+        let endpoint = "/v5/account/mmp-state";
+
+        let params = json!({
+            "baseCoin": base_coin,
+        });
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("result")
+            .cloned()
+            .context("Failed to extract mmp state from response")?;
+
+        let states: Vec<MmpState> = serde_json::from_value(list)?;
+        Ok(states)
+    }
+
+    /// Moves `amount` of `coin` between two wallets of this account. `transfer_id` must be a
+    /// caller-generated UUID; resending the same id never transfers twice.
+    pub async fn inter_transfer(&self, transfer_id: &str, coin: &str, amount: f64, from: AccountType, to: AccountType) -> anyhow::Result<TransferReceipt> {
+        let endpoint = "/v5/asset/transfer/inter-transfer";
+
+        ensure!(is_uuid(transfer_id), "transfer_id must be a UUID");
+        ensure!(amount > 0.0, "amount must be positive");
+        ensure!(from != to, "from and to account types are the same");
+
+        let params = json!({
+            "transferId": transfer_id,
+            "coin": coin,
+            "amount": amount.to_string(),
+            "fromAccountType": from,
+            "toAccountType": to,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let receipt: TransferReceipt = serde_json::from_value(resp.result)?;
+        self.complete_receipt(receipt, false).await
+    }
+
+    /// Moves `amount` of `coin` between the master account and its sub-accounts (master key only),
+    /// `from` and `to` being `(member id, wallet)`. `transfer_id` must be a caller-generated UUID.
+    pub async fn universal_transfer(&self, transfer_id: &str, coin: &str, amount: f64, from: (u64, AccountType), to: (u64, AccountType)) -> anyhow::Result<TransferReceipt> {
+        let endpoint = "/v5/asset/transfer/universal-transfer";
+
+        ensure!(is_uuid(transfer_id), "transfer_id must be a UUID");
+        ensure!(amount > 0.0, "amount must be positive");
+
+        let params = json!({
+            "transferId": transfer_id,
+            "coin": coin,
+            "amount": amount.to_string(),
+            "fromMemberId": from.0,
+            "toMemberId": to.0,
+            "fromAccountType": from.1,
+            "toAccountType": to.1,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let receipt: TransferReceipt = serde_json::from_value(resp.result)?;
+        self.complete_receipt(receipt, true).await
+    }
+
+    pub async fn get_inter_transfers(&self, coin_op: Option<&str>, status_op: Option<TransferStatus>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<TransferRecord>> {
+        let endpoint = "/v5/asset/transfer/query-inter-transfer-list";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        if let Some(status) = status_op {
+            ensure!(!status.eq(&TransferStatus::StatusUnknown), "cannot filter on TransferStatus::StatusUnknown");
+            params["status"] = json!(status);
+        }
+
+        self.get_all_pages_in_range(endpoint, params, start_time, end_time).await
+    }
+
+    pub async fn get_universal_transfers(&self, coin_op: Option<&str>, status_op: Option<TransferStatus>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<TransferRecord>> {
+        let endpoint = "/v5/asset/transfer/query-universal-transfer-list";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        if let Some(status) = status_op {
+            ensure!(!status.eq(&TransferStatus::StatusUnknown), "cannot filter on TransferStatus::StatusUnknown");
+            params["status"] = json!(status);
+        }
+
+        self.get_all_pages_in_range(endpoint, params, start_time, end_time).await
+    }
+
+    // older responses carry no status, look it up rather than guessing
+    async fn complete_receipt(&self, mut receipt: TransferReceipt, universal: bool) -> anyhow::Result<TransferReceipt> {
+        if receipt.status == TransferStatus::StatusUnknown {
+            if let Some(status) = self.find_transfer_status(&receipt.transfer_id, universal).await? {
+                receipt.status = status;
+            }
+        }
+
+        Ok(receipt)
+    }
+
+    /// Current status of a transfer by its id, `universal` selecting the sub-account transfer list.
+    /// See `wait_for_transfer` to poll until it settles.
+    pub async fn get_transfer_status(&self, transfer_id: &str, universal: bool) -> anyhow::Result<TransferStatus> {
+        self.find_transfer_status(transfer_id, universal).await?
+            .with_context(|| format!("transfer {transfer_id} not found"))
+    }
+
+    /// Polls a transfer every second until it is SUCCESS or FAILED, failing after `timeout`.
+    /// A transfer not listed yet counts as still in flight.
+    pub async fn wait_for_transfer(&self, transfer_id: &str, universal: bool, timeout: Duration) -> anyhow::Result<TransferStatus> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let status_op = self.find_transfer_status(transfer_id, universal).await?;
+
+            if let Some(status) = status_op {
+                if status.is_final() {
+                    return Ok(status);
+                }
+            }
+
+            ensure!(tokio::time::Instant::now() < deadline, "transfer {transfer_id} still {status_op:?} after {timeout:?}");
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn find_transfer_status(&self, transfer_id: &str, universal: bool) -> anyhow::Result<Option<TransferStatus>> {
+        let endpoint = if universal {
+            "/v5/asset/transfer/query-universal-transfer-list"
+        } else {
+            "/v5/asset/transfer/query-inter-transfer-list"
+        };
+
+        let params = json!({
+            "transferId": transfer_id,
+        });
+
+        let records: Vec<TransferRecord> = self.get_all_pages(endpoint, params).await?;
+
+        Ok(records.into_iter().find(|r| r.transfer_id == transfer_id).map(|r| r.status))
+    }
+
+    /// Coins that can be moved from one wallet type to another.
+    pub async fn get_transferable_coins(&self, from: AccountType, to: AccountType) -> anyhow::Result<Vec<String>> {
+        let endpoint = "/v5/asset/transfer/query-transfer-coin-list";
+
+        let params = json!({
+            "fromAccountType": from,
+            "toAccountType": to,
+        });
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let list = resp.result
+            .get("list")
+            .cloned()
+            .context("Failed to extract coin list from response")?;
+
+        let coins: Vec<String> = serde_json::from_value(list)?;
+        Ok(coins)
+    }
+
+    /// Per-chain deposit and withdrawal parameters for `coin_op`, or every coin.
+    pub async fn get_coin_info(&self, coin_op: Option<&str>) -> anyhow::Result<Vec<CoinMetadata>> {
+        let endpoint = "/v5/asset/coin/query-info";
+
+        let mut params = json!({});
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let rows = resp.result
+            .get("rows")
+            .cloned()
+            .context("Failed to extract rows from response")?;
+
+        let coins: Vec<CoinMetadata> = serde_json::from_value(rows)?;
+        Ok(coins)
+    }
+
+    pub async fn get_chain_info(&self, coin: &str, chain: &str) -> anyhow::Result<ChainInfo> {
+        let coins = self.get_coin_info(Some(coin)).await?;
+
+        coins.iter()
+            .find(|c| c.coin == coin)
+            .and_then(|c| c.chain(chain))
+            .cloned()
+            .with_context(|| format!("{coin} is not supported on {chain}"))
+    }
+
+    /// Master account deposit addresses for `coin`, on `chain_op` or every chain.
+    pub async fn get_deposit_addresses(&self, coin: &str, chain_op: Option<&str>) -> anyhow::Result<DepositAddresses> {
+        let endpoint = "/v5/asset/deposit/query-address";
+
+        let mut params = json!({
+            "coin": coin,
+        });
+
+        if let Some(chain) = chain_op {
+            params["chainType"] = json!(chain);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let addresses: DepositAddresses = serde_json::from_value(resp.result)?;
+        Ok(addresses)
+    }
+
+    /// Deposit address of a sub-account (master key only).
+    pub async fn get_sub_member_deposit_address(&self, sub_member_id: &str, coin: &str, chain: &str) -> anyhow::Result<DepositAddress> {
+        let endpoint = "/v5/asset/deposit/query-sub-member-address";
+
+        let params = json!({
+            "coin": coin,
+            "chainType": chain,
+            "subMemberId": sub_member_id,
+        });
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        // a single chain object here, not a list
+        let chain_json = resp.result
+            .get("chains")
+            .cloned()
+            .context("Failed to extract chains from response")?;
+
+        let address: DepositAddress = serde_json::from_value(chain_json)?;
+        Ok(address)
+    }
+
+    /// On-chain deposits, following every page and 30-day window.
+    pub async fn get_deposit_records(&self, coin_op: Option<&str>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<DepositRecord>> {
+        let endpoint = "/v5/asset/deposit/query-record";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        self.get_all_pages_in_windows(endpoint, params, "rows", ASSET_QUERY_WINDOW_MS, start_time, end_time).await
+    }
+
+    /// On-chain deposits into a sub-account (master key only).
+    pub async fn get_sub_member_deposit_records(&self, sub_member_id: &str, coin_op: Option<&str>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<DepositRecord>> {
+        let endpoint = "/v5/asset/deposit/query-sub-member-record";
+
+        let mut params = json!({
+            "subMemberId": sub_member_id,
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        self.get_all_pages_in_windows(endpoint, params, "rows", ASSET_QUERY_WINDOW_MS, start_time, end_time).await
+    }
+
+    /// Off-chain deposits from other bybit users.
+    pub async fn get_internal_deposit_records(&self, coin_op: Option<&str>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<InternalDepositRecord>> {
+        let endpoint = "/v5/asset/deposit/query-internal-record";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        self.get_all_pages_in_windows(endpoint, params, "rows", ASSET_QUERY_WINDOW_MS, start_time, end_time).await
+    }
+
+    /// Withdrawals, following every page and 30-day window. Bybit only returns on-chain
+    /// withdrawals unless `withdraw_type_op` says otherwise.
+    pub async fn get_withdrawal_records(&self, coin_op: Option<&str>, withdraw_type_op: Option<WithdrawType>, start_time: Option<u64>, end_time: Option<u64>) -> anyhow::Result<Vec<WithdrawalRecord>> {
+        let endpoint = "/v5/asset/withdraw/query-record";
+
+        let mut params = json!({
+            "limit": "50",
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        if let Some(withdraw_type) = withdraw_type_op {
+            params["withdrawType"] = json!((withdraw_type as i32).to_string());
+        }
+
+        self.get_all_pages_in_windows(endpoint, params, "rows", ASSET_QUERY_WINDOW_MS, start_time, end_time).await
+    }
+
+    /// Installs `policy`, keeping what has already been withdrawn today under the previous one.
+    pub fn set_withdrawal_policy(&self, mut policy: WithdrawalPolicy) -> anyhow::Result<()> {
+        let mut current = self.withdrawal_policy.lock().map_err(|_| anyhow!("withdrawal policy lock poisoned"))?;
+        if let Some(previous) = current.as_ref() {
+            policy.carry_usage_from(previous);
+        }
+        *current = Some(policy);
+        Ok(())
+    }
+
+    /// Submits a withdrawal once it passes the configured `WithdrawalPolicy` and its confirmation
+    /// callback. Returns the withdrawal id.
+    pub async fn withdraw(&self, request: &WithdrawRequest) -> anyhow::Result<String> {
+        let endpoint = "/v5/asset/withdraw/create";
+
+        let now = get_timestamp();
+
+        let confirmation = {
+            let mut guard = self.withdrawal_policy.lock().map_err(|_| anyhow!("withdrawal policy lock poisoned"))?;
+            let policy = guard.as_mut().context("no withdrawal policy set")?;
+            policy.reserve(request, now as u64)?;
+            policy.confirmation()
+        };
+
+        if let Some(confirm) = confirmation {
+            if !confirm(request) {
+                self.release_withdrawal(request, now as u64);
+                bail!("withdrawal of {} {} was not confirmed", request.amount, request.coin);
+            }
+        }
+
+        let params = match request.into_json(now) {
+            Ok(params) => params,
+            Err(e) => {
+                self.release_withdrawal(request, now as u64);
+                return Err(e);
+            }
+        };
+
+        // a transport error may still have reached bybit, so the reservation is only
+        // given back when bybit explicitly rejects the withdrawal
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            self.release_withdrawal(request, now as u64);
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let id = resp.result["id"].as_str().context("Failed to extract withdrawal id")?;
+        Ok(id.to_string())
+    }
+
+    fn release_withdrawal(&self, request: &WithdrawRequest, now_ms: u64) {
+        if let Ok(mut guard) = self.withdrawal_policy.lock() {
+            if let Some(policy) = guard.as_mut() {
+                policy.release(request, now_ms);
+            }
+        }
+    }
+
+    /// Cancels a withdrawal that is still pending. Not gated by the policy, and does not give
+    /// back the amount counted against the daily limit.
+    pub async fn cancel_withdrawal(&self, withdraw_id: &str) -> anyhow::Result<()> {
+        let endpoint = "/v5/asset/withdraw/cancel";
+
+        let params = json!({
+            "id": withdraw_id,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        ensure!(resp.result["status"].as_i64() == Some(1), "withdrawal {withdraw_id} was not cancelled");
+
+        Ok(())
+    }
+
+    /// Coins convertible from (or to, per `side`) in `account_type`, with balances and limits.
+    pub async fn get_convert_coins(&self, account_type: ConvertAccountType, coin_op: Option<&str>, side: ConvertSide) -> anyhow::Result<Vec<ConvertCoin>> {
+        let endpoint = "/v5/asset/exchange/query-coin-list";
+
+        let mut params = json!({
+            "accountType": account_type,
+            "side": (side as i32).to_string(),
+        });
+
+        if let Some(coin) = coin_op {
+            params["coin"] = json!(coin);
+        }
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let coins = resp.result
+            .get("coins")
+            .cloned()
+            .context("Failed to extract coins from response")?;
+
+        let coins: Vec<ConvertCoin> = serde_json::from_value(coins)?;
+        Ok(coins)
+    }
+
+    /// First phase of a conversion: a quote for selling `from_amount` of `from_coin`, valid
+    /// until `ConvertQuote::expired_time`. Nothing is converted until `execute_convert`.
+    pub async fn request_convert_quote(&self, account_type: ConvertAccountType, from_coin: &str, to_coin: &str, from_amount: f64) -> anyhow::Result<ConvertQuote> {
+        let endpoint = "/v5/asset/exchange/quote-apply";
+
+        ensure!(from_amount > 0.0, "from_amount must be positive");
+        ensure!(from_coin != to_coin, "from_coin and to_coin are the same");
+
+        let params = json!({
+            "fromCoin": from_coin,
+            "toCoin": to_coin,
+            "requestCoin": from_coin,
+            "requestAmount": from_amount.to_string(),
+            "accountType": account_type,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let mut quote: ConvertQuote = serde_json::from_value(resp.result)?;
+        quote.account_type = account_type;
+        Ok(quote)
+    }
+
+    /// Second phase: accepts `quote`. Refused locally once the quote has expired.
+    pub async fn execute_convert(&self, quote: &ConvertQuote) -> anyhow::Result<ConvertStatus> {
+        let endpoint = "/v5/asset/exchange/convert-execute";
+
+        ensure!(!quote.is_expired(get_timestamp() as u64), "quote {} expired at {}", quote.quote_tx_id, quote.expired_time);
+
+        let params = json!({
+            "quoteTxId": quote.quote_tx_id,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let status: ConvertStatus = serde_json::from_value(resp.result["exchangeStatus"].clone())?;
+        Ok(status)
+    }
+
+    pub async fn get_convert_result(&self, quote_tx_id: &str, account_type: ConvertAccountType) -> anyhow::Result<ConvertRecord> {
+        let endpoint = "/v5/asset/exchange/convert-result-query";
+
+        let params = json!({
+            "quoteTxId": quote_tx_id,
+            "accountType": account_type,
+        });
+
+        let resp = self.signed_get(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let record = resp.result
+            .get("result")
+            .cloned()
+            .context("Failed to extract result from response")?;
+
+        let record: ConvertRecord = serde_json::from_value(record)?;
+        Ok(record)
+    }
+
+    /// Executes `quote` and polls its result every `interval` until it settles or `timeout` passes.
+    pub async fn convert(&self, quote: &ConvertQuote, interval: Duration, timeout: Duration) -> anyhow::Result<ConvertRecord> {
+        self.execute_convert(quote).await?;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let record = self.get_convert_result(&quote.quote_tx_id, quote.account_type).await?;
+            if record.status.is_final() {
+                return Ok(record);
+            }
+
+            ensure!(tokio::time::Instant::now() < deadline, "convert {} still {:?} after {:?}", quote.quote_tx_id, record.status, timeout);
+        }
+    }
+
+    /// Every conversion in `account_types` (all of them if empty), newest first.
+    pub async fn get_convert_history(&self, account_types: &[ConvertAccountType]) -> anyhow::Result<Vec<ConvertRecord>> {
+        let endpoint = "/v5/asset/exchange/query-convert-history";
+        let limit = 100;
+
+        let mut params = json!({
+            "limit": limit.to_string(),
+        });
+
+        if !account_types.is_empty() {
+            let types: Vec<Value> = account_types.iter().map(|t| json!(t)).collect();
+            let types: Vec<&str> = types.iter().filter_map(Value::as_str).collect();
+            params["accountType"] = json!(types.join(","));
+        }
+
+        let mut records: Vec<ConvertRecord> = vec![];
+
+        // paged by page number rather than cursor
+        for index in 1.. {
+            params["index"] = json!(index.to_string());
+
+            let resp = self.signed_get(endpoint, params.clone()).await?;
+
+            if resp.ret_code != 0 {
+                bail!("bybit err resp: {}", resp.ret_msg);
+            }
+
+            let list = resp.result
+                .get("list")
+                .cloned()
+                .context("Failed to extract list from response")?;
+
+            let page: Vec<ConvertRecord> = serde_json::from_value(list)?;
+            let page_len = page.len();
+            records.extend(page);
+
+            if page_len < limit {
+                break;
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Creates a sub-account (master key only). `username` is 6-16 characters mixing letters and digits.
+    pub async fn create_sub_member(&self, username: &str, member_type: SubMemberType, note_op: Option<&str>) -> anyhow::Result<SubMember> {
+        let endpoint = "/v5/user/create-sub-member";
+
+        ensure!((6..=16).contains(&username.len()), "username must be 6 to 16 characters");
+        ensure!(username.chars().all(|c| c.is_ascii_alphanumeric()), "username must only contain letters and digits");
+        ensure!(
+            username.chars().any(|c| c.is_ascii_alphabetic()) && username.chars().any(|c| c.is_ascii_digit()),
+            "username must contain both letters and digits"
+        );
+
+        let mut params = json!({
+            "username": username,
+            "memberType": member_type as i32,
+        });
+
+        if let Some(note) = note_op {
+            params["note"] = json!(note);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let member: SubMember = serde_json::from_value(resp.result)?;
+        Ok(member)
+    }
+
+    pub async fn get_sub_members(&self) -> anyhow::Result<Vec<SubMember>> {
+        let endpoint = "/v5/user/query-sub-members";
+
+        let resp = self.signed_get(endpoint, json!({})).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let members = resp.result
+            .get("subMembers")
+            .cloned()
+            .context("Failed to extract subMembers from response")?;
+
+        let members: Vec<SubMember> = serde_json::from_value(members)?;
+        Ok(members)
+    }
+
+    pub async fn freeze_sub_member(&self, sub_uid: u64, frozen: bool) -> anyhow::Result<()> {
+        let endpoint = "/v5/user/frozen-sub-member";
+
+        let params = json!({
+            "subuid": sub_uid,
+            "frozen": frozen as i32,
+        });
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Creates an API key for sub-account `sub_uid` (master key only). The secret is only
+    /// returned here.
+    pub async fn create_sub_api_key(&self, sub_uid: u64, settings: &ApiKeySettings) -> anyhow::Result<SubApiKey> {
+        let endpoint = "/v5/user/create-sub-api";
+
+        let mut params = settings.into_json()?;
+        params["subuid"] = json!(sub_uid);
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let key: SubApiKey = serde_json::from_value(resp.result)?;
+        Ok(key)
+    }
+
+    /// Replaces the read-only flag, IP binding and permissions of a sub-account key, `api_key_op`
+    /// when called with the master key or the calling key itself when None. The note is not updated.
+    pub async fn update_sub_api_key(&self, api_key_op: Option<&str>, settings: &ApiKeySettings) -> anyhow::Result<SubApiKey> {
+        let endpoint = "/v5/user/update-sub-api";
+
+        let mut params = settings.into_json()?;
+        if let Some(obj) = params.as_object_mut() {
+            obj.remove("note");
+        }
+
+        if let Some(api_key) = api_key_op {
+            params["apikey"] = json!(api_key);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        let key: SubApiKey = serde_json::from_value(resp.result)?;
+        Ok(key)
+    }
+
+    /// Deletes a sub-account key, `api_key_op` when called with the master key or the calling key
+    /// itself when None.
+    pub async fn delete_sub_api_key(&self, api_key_op: Option<&str>) -> anyhow::Result<()> {
+        let endpoint = "/v5/user/delete-sub-api";
+
+        let mut params = json!({});
+
+        if let Some(api_key) = api_key_op {
+            params["apikey"] = json!(api_key);
+        }
+
+        let resp = self.signed_post(endpoint, params).await?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(())
+    }
+
+    /// Server time in ms.
+    pub async fn get_server_time(&self) -> anyhow::Result<u64> {
+        let endpoint = "/v5/market/time";
+
+        let resp = self.get_request_no_sign(endpoint, json!({})).await?;
+        let txt = resp.text().await?;
+
+        let resp: BybitResponse = serde_json::from_str(&txt)?;
+
+        if resp.ret_code != 0 {
+            bail!("bybit err resp: {}", resp.ret_msg);
+        }
+
+        Ok(resp.time as u64)
+    }
+
+    /// Server clock minus the local clock in ms, measured against the midpoint of the request.
+    pub async fn get_clock_offset(&self) -> anyhow::Result<i64> {
+        let sent = get_timestamp() as i64;
+        let server_time = self.get_server_time().await? as i64;
+        let received = get_timestamp() as i64;
+
+        Ok(server_time - (sent + received) / 2)
+    }
+
+    /// Details of the calling API key: permissions, IP binding and expiry.
+    pub async fn get_api_key_info(&self) -> anyhow::Result<ApiKeyInfo> {
+        let endpoint = "/v5/user/query-api";
+
+        let resp = self.signed_get(endpoint, json!({})).await?;
+
+        if resp.ret_code != 0 {
+            match api_key_error(resp.ret_code) {
+                Some(reason) => bail!("bybit err resp: {} ({reason})", resp.ret_msg),
+                None => bail!("bybit err resp: {}", resp.ret_msg),
+            }
+        }
+
+        let info: ApiKeyInfo = serde_json::from_value(resp.result)?;
+        Ok(info)
+    }
+
+    /// Startup check of the key and clock against `policy`. Fails if the key is rejected
+    /// (invalid, wrong IP, expired); otherwise returns a report whose `ensure_ready` fails on
+    /// any policy violation, e.g. a read-only or nearly expired key.
+    pub async fn preflight(&self, policy: &PreflightPolicy) -> anyhow::Result<PreflightReport> {
+        let clock_offset_ms = self.get_clock_offset().await?;
+        let key = self.get_api_key_info().await?;
+
+        Ok(PreflightReport::new(key, clock_offset_ms, policy))
+    }
+}
+
+
+// https://bybit-exchange.github.io/docs/v5/ws/connect#authentication
+async fn open_dcp_session(api_key: &str, api_secret: &str, topics: &[&str]) -> anyhow::Result<WsStream> {
+    let (mut socket, _) = connect_async(PRIVATE_WS_URL).await?;
+
+    let expires = get_timestamp() + 10_000;
+    let signature = hmac_sha256_hex(api_secret, &format!("GET/realtime{expires}"))?;
+
+    let auth = json!({"op": "auth", "args": [api_key, expires, signature]});
+    socket.send(Message::Text(auth.to_string())).await?;
+    expect_ws_ack(&mut socket, "auth").await?;
+
+    let subscribe = json!({"op": "subscribe", "args": topics});
+    socket.send(Message::Text(subscribe.to_string())).await?;
+    expect_ws_ack(&mut socket, "subscribe").await?;
+
+    Ok(socket)
+}
+
+async fn expect_ws_ack(socket: &mut WsStream, op: &str) -> anyhow::Result<()> {
+    let ack = tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(message) = socket.next().await {
+            if let Message::Text(text) = message? {
+                let resp: Value = serde_json::from_str(&text)?;
+
+                if resp["op"] == op {
+                    ensure!(resp["success"] == true, "bybit ws {op} failed: {}", resp["ret_msg"]);
+                    return Ok(());
+                }
+            }
+        }
+
+        bail!("bybit ws closed before {op} ack")
+    });
+
+    ack.await.with_context(|| format!("bybit ws {op} timed out"))?
+}
+
+// pings until the connection drops
+async fn keep_dcp_session(socket: &mut WsStream) -> anyhow::Result<()> {
+    let mut ping = tokio::time::interval(WS_PING_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                socket.send(Message::Text(json!({"op": "ping"}).to_string())).await?;
+            },
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | None => bail!("bybit ws closed"),
+                Some(Err(e)) => return Err(e.into()),
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+// ret codes meaning the key itself was refused
+fn api_key_error(ret_code: u64) -> Option<&'static str> {
+    match ret_code {
+        10003 => Some("invalid api key"),
+        10004 => Some("invalid signature, check the api secret"),
+        10005 => Some("permission denied for this api key"),
+        10010 => Some("request ip is not bound to this api key"),
+        33004 => Some("api key expired"),
+        _ => None,
+    }
+}
+
+// options fee rates are per base coin, e.g. BTC for BTC-27SEP24-60000-C
+fn fee_rate_key(category: Category, symbol: &str) -> &str {
+    if category.eq(&Category::Option) {
+        symbol.split('-').next().unwrap_or(symbol)
+    } else {
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use utils::unlock_keys;
+    use super::*;
+
+    #[tokio::test]
+    pub async fn test_cancel_order() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        let order_id = String::from("xxxxx");
+        bybit.cancel_order(Category::Linear, "ETHUSDT", OrderId::OrderID(order_id), None).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_cancel_all_orders() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        bybit.cancel_all_orders(Category::Linear, Some(OrderScope::Symbol(String::from("ETHUSDT"))), None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_cancel_all_stop_orders() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+
+        let stops = bybit.cancel_all_orders(Category::Linear, Some(OrderScope::SettleCoin(String::from("USDT"))), Some(OrderFilter::StopOrder), Some(StopOrderType::Stop)).await.unwrap();
+        dbg!(stops);
+    }
+
+    #[tokio::test]
+    pub async fn test_create_limit_order() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let proxy_url = env::var("proxy_url").ok();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), proxy_url).unwrap();
+        let order = OrderRequest::limit("ETHUSDT", TradeDirection::Buy, 0.1, 3000.21).time_in_force(TimeInForce::GTC);
+        let resp = bybit.create_order(Category::Linear, order).await.unwrap();
+        dbg!(resp);
+    }
+
+    #[tokio::test]
+    pub async fn test_create_market_order() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        let order = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.01);
+        bybit.create_order(Category::Linear, order).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_create_order_with_tpsl() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        let order = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.01)
+            .take_profit(4000.0)
+            .stop_loss(2500.0)
+            .tpsl_mode(TpslMode::Full)
+            .tp_trigger_by(TriggerBy::MarkPrice)
+            .sl_trigger_by(TriggerBy::MarkPrice);
+        let resp = bybit.create_order(Category::Linear, order).await.unwrap();
+        dbg!(resp);
+    }
+
+    #[tokio::test]
+    pub async fn test_create_list_cancel_stop_order() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        let order = OrderRequest::stop_limit("ETHUSDT", TradeDirection::Buy, 0.01, 4100.0, 4000.0, TriggerDirection::RisesTo)
+            .trigger_by(TriggerBy::MarkPrice);
+        let resp = bybit.create_order(Category::Linear, order).await.unwrap();
+
+        let orders = bybit.get_orders(Category::Linear, "ETHUSDT", None, Some(OrderFilter::StopOrder)).await.unwrap();
+        dbg!(&orders);
+
+        bybit.cancel_order(Category::Linear, "ETHUSDT", OrderId::OrderID(resp.order_id), None).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_set_trading_stop() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        let request = TradingStopRequest::new("ETHUSDT", PositionIdx::OneWay, TpslMode::Full)
+            .stop_loss(2600.0)
+            .trailing_stop(50.0, Some(3500.0));
+        bybit.set_trading_stop(Category::Linear, request).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_set_leverage() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        bybit.set_leverage(Category::Linear, "ETHUSDT", 5.0, 5.0).await.unwrap();
+        // second call is a no-op on bybit's side and must not error
+        bybit.set_leverage(Category::Linear, "ETHUSDT", 5.0, 5.0).await.unwrap();
+    }
 
-        for contract in contract_list.iter() {
-            let info: ContractInfo = serde_json::from_value(contract.clone())?;
-            map.insert(info.symbol.clone(), info);
-        }
+    #[tokio::test]
+    pub async fn test_switch_position_mode() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        Ok(map)
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        bybit.switch_position_mode(Category::Linear, None, Some("USDT"), PositionMode::OneWay).await.unwrap();
     }
 
-    pub async fn get_instrument_info_all(&self, category: Category) -> anyhow::Result<HashMap<String, ContractInfo>> {
-        let endpoint = "/v5/market/instruments-info";
-        let mut map: HashMap<String, ContractInfo> = HashMap::default();
-        let mut cursor: Option<String> = None;
+    #[tokio::test]
+    pub async fn test_create_batch_market_orders() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        loop {
-            let mut params = json!({
-                "category": category,
-                "limit": "1000",
-            });
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-            if let Some(ref c) = cursor {
-                params["cursor"] = json!(c);
-            }
+        let new_orders = vec![
+            OrderRequest::market("BLASTUSDT", TradeDirection::Buy, 1000.0),
+            OrderRequest::market("BLASTUSDT", TradeDirection::Buy, 1200.0),
+        ];
 
-            let resp = self.get_request_no_sign(endpoint, params).await?;
-            let txt = resp.text().await?;
-            let resp: BybitResponse = serde_json::from_str(&txt)?;
+        let receipts = bybit.batch_create_order(Category::Linear,new_orders).await.unwrap();
+        dbg!(receipts);
+    }
 
-            if resp.ret_code != 0 {
-                bail!("bybit err resp: {}", resp.ret_msg);
-            }
+    #[tokio::test]
+    pub async fn test_set_dcp_window() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-            let contract_list = resp.result
-                .get("list")
-                .and_then(Value::as_array)
-                .context("Failed to extract contract list from response")?;
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        bybit.set_dcp_window(DcpProduct::Derivatives, 30).await.unwrap();
 
-            for contract in contract_list.iter() {
-                let info: ContractInfo = serde_json::from_value(contract.clone())?;
-                map.insert(info.symbol.clone(), info);
-            }
+        let settings = bybit.get_account_info().await.unwrap();
+        dbg!(settings.dcp_status);
+    }
 
-            let next_cursor = resp.result
-                .get("nextPageCursor")
-                .and_then(Value::as_str)
-                .unwrap_or("");
+    // stopping the keepalive cancels every derivatives order on the account once the window lapses
+    #[tokio::test]
+    #[ignore]
+    pub async fn test_dcp_keepalive() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-            if next_cursor.is_empty() {
-                break;
-            }
-            cursor = Some(next_cursor.to_string());
-        }
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        bybit.start_dcp_keepalive(&[DcpProduct::Derivatives], 30).await.unwrap();
+        assert!(bybit.dcp_keepalive_running());
 
-        Ok(map)
+        bybit.stop_dcp_keepalive().unwrap();
+        assert!(!bybit.dcp_keepalive_running());
     }
 
-    pub async fn get_futures_tickers(&self, symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, TickerData>> {
-
-        let endpoint = "/v5/market/tickers";
+    #[tokio::test]
+    pub async fn test_get_orders() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let mut params = json!({
-            "category": Category::Linear,
-        });
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+        // market order d3075c7f-0cae-4bc0-9ace-e9b5d9055326
+        // limit order 1f6b52d0-0d38-4c44-a558-0d2619b58061
+        let order_id = String::from("d3075c7f-0cae-4bc0-9ace-e9b5d9055326");
+        let orders = bybit.get_orders(Category::Linear, "ETHUSDT",Some(OrderId::OrderID(order_id)), None).await.unwrap();
+        dbg!(orders);
+    }
 
-        if let Some(symbol) = symbol_op {
-            params["symbol"] = json!(symbol);
-        }
+    #[tokio::test]
+    pub async fn test_get_executions() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let resp = self.get_request_no_sign(endpoint, params).await?;
-        let txt = resp.text().await?;
-        //println!("resp: {txt}");
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        //std::fs::write("ftickersraw.json", &txt).unwrap();
+        let start_time = get_timestamp() as u64 - 2 * QUERY_WINDOW_MS;
+        let executions = bybit.get_executions(Category::Linear, None, None, Some(start_time), None).await.unwrap();
+        dbg!(executions.len());
+        dbg!(executions.first());
+    }
 
-        let resp: BybitResponse = serde_json::from_str(&txt)?;
+    #[tokio::test]
+    pub async fn test_get_closed_pnl() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        if resp.ret_code != 0 {
-            bail!("bybit err resp: {}", resp.ret_msg);
-        }
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        //dbg!(&resp.result);
+        let start_time = get_timestamp() as u64 - 30 * 24 * 60 * 60 * 1000;
+        let closed = bybit.get_closed_pnl(Category::Linear, None, Some(start_time), None).await.unwrap();
+        let total: f64 = closed.iter().map(|c| c.closed_pnl).sum();
+        dbg!(closed.len(), total);
+    }
 
-        let contract_list = resp.result
-        .get("list")
-        .and_then(Value::as_array)
-        .context("Failed to extract contract list from response")?;
+    #[tokio::test]
+    pub async fn test_get_open_orders() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let mut map: HashMap<String, TickerData> = HashMap::default();
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        for contract in contract_list.iter() {
-            let info: TickerData = serde_json::from_value(contract.clone())?;
-            map.insert(info.symbol.clone(), info);
-        }
+        let stops = bybit.get_open_orders(Category::Linear, Some(OrderScope::SettleCoin(String::from("USDT"))), OrderListing::OpenOrders, Some(OrderFilter::StopOrder)).await.unwrap();
+        dbg!(stops.len());
 
-        Ok(map)
+        let snapshot = bybit.get_all_open_orders().await.unwrap();
+        dbg!(snapshot.len());
     }
 
-    pub async fn get_spot_tickers(&self, symbol_op: Option<&str>) -> anyhow::Result<HashMap<String, SpotTickerData>> {
+    #[test]
+    pub fn test_order_listing() {
+        // openOnly=0 lists open orders, 1 only recently closed ones
+        assert_eq!(json!(OrderListing::OpenOrders), "0");
+        assert_eq!(json!(OrderListing::RecentlyClosed), "1");
+    }
 
-        let endpoint = "/v5/market/tickers";
+    // turns on spot margin and buys ETH on borrowed funds
+    #[tokio::test]
+    #[ignore]
+    pub async fn test_spot_margin() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let mut params = json!({
-            "category": Category::Spot,
-        });
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        if let Some(symbol) = symbol_op {
-            params["symbol"] = json!(symbol);
-        }
+        bybit.set_spot_margin_mode(true).await.unwrap();
+        bybit.set_spot_margin_leverage(3.0).await.unwrap();
+        dbg!(bybit.get_spot_margin_state().await.unwrap());
+        dbg!(bybit.get_spot_margin_interest_rate_history("USDT", None, None, None).await.unwrap());
 
-        let resp = self.get_request(endpoint, params).await?;
-        let txt = resp.text().await?;
-        //println!("resp: {txt}");
+        let order = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.01).is_leverage(true);
+        let resp = bybit.create_order(Category::Spot, order).await.unwrap();
+        dbg!(resp);
+    }
 
-        let resp: BybitResponse = serde_json::from_str(&txt)?;
+    #[tokio::test]
+    pub async fn test_fee_rates() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        if resp.ret_code != 0 {
-            bail!("bybit err resp: {}", resp.ret_msg);
-        }
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        //dbg!(&resp.result);
+        let rate = bybit.fee_rate(Category::Linear, "ETHUSDT").await.unwrap();
+        dbg!(&rate);
+        assert!(bybit.cached_fee_rate(Category::Linear, "ETHUSDT").is_some());
 
-        let contract_list = resp.result
-        .get("list")
-        .and_then(Value::as_array)
-        .context("Failed to extract contract list from response")?;
+        let order = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1);
+        let cost = bybit.estimate_order_cost(Category::Linear, &order, Some(3000.0), None).await.unwrap();
+        dbg!(cost);
+    }
 
-        let mut map: HashMap<String, SpotTickerData> = HashMap::default();
+    #[tokio::test]
+    pub async fn test_get_transaction_log() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        for contract in contract_list.iter() {
-            let info: SpotTickerData = serde_json::from_value(contract.clone())?;
-            map.insert(info.symbol.clone(), info);
-        }
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        Ok(map)
+        let start_time = get_timestamp() as u64 - 2 * QUERY_WINDOW_MS;
+        let funding = bybit.get_transaction_log(Some(Category::Linear), Some("USDT"), Some(TransactionType::Settlement), Some(start_time), None).await.unwrap();
+        let total: f64 = funding.iter().map(|t| t.funding).sum();
+        dbg!(funding.len(), total);
     }
 
-    pub async fn get_options_tickers(&self, base_coin: &str) -> anyhow::Result<Vec<OptionTickerData>> {
+    #[tokio::test]
+    pub async fn test_get_account_info() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let endpoint = "/v5/market/tickers";
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let params = json!({
-            "category": Category::Option,
-            "baseCoin": base_coin
-        });
+        dbg!(bybit.get_account_info().await.unwrap());
+    }
 
-        let resp = self.get_request(endpoint, params).await?;
-        let txt = resp.text().await?;
-        //println!("resp: {txt}");
+    // flips collateral coins and the account margin mode
+    #[tokio::test]
+    #[ignore]
+    pub async fn test_collateral_settings() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let resp: BybitResponse = serde_json::from_str(&txt)?;
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        if resp.ret_code != 0 {
-            bail!("bybit err resp: {}", resp.ret_msg);
-        }
+        let info = bybit.get_collateral_info(Some("ETH")).await.unwrap();
+        dbg!(&info);
 
-        let list_value = resp
-        .result
-        .get("list")
-        .cloned()                      // turn &Value into Value
-        .context("failed to extract contract list from response")?;
+        let states = bybit.set_collateral_switch_batch(&[("ETH", true), ("SOL", false)]).await.unwrap();
+        dbg!(states);
 
-        let data: Vec<OptionTickerData> = serde_json::from_value(list_value)?;
-        Ok(data)
+        bybit.set_margin_mode(MarginMode::RegularMargin).await.unwrap();
     }
 
-    pub async fn get_position_info(&self, category: Category,settle_coin: Option<&str>) -> anyhow::Result<HashMap<String, PositionInfo>> {
-
-        let endpoint = "/v5/position/list";
+    #[tokio::test]
+    pub async fn test_mmp() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let mut params = json!({
-            "category": category,
-        });
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        if category.eq(&Category::Linear) && settle_coin.is_none() {
-            bail!("settleCoin is required for linear");
-        }
+        let config = MmpConfig::new("ETH", 5000, 100000, 10.0, 5.0);
+        bybit.set_mmp(&config).await.unwrap();
+        dbg!(bybit.get_mmp_state("ETH").await.unwrap());
+        bybit.reset_mmp("ETH").await.unwrap();
+    }
 
-        if let Some(coin) = settle_coin {
-            params["settleCoin"] = json!(coin);
-        }
+    // moves 1 USDT from FUND to UNIFIED on the account behind the keys
+    #[tokio::test]
+    #[ignore]
+    pub async fn test_inter_transfer() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let resp = self.get_request(endpoint, params).await?;
-        let txt = resp.text().await?;
-        //println!("resp: {txt}");
+        let coins = bybit.get_transferable_coins(AccountType::FUND, AccountType::UNIFIED).await.unwrap();
+        assert!(coins.iter().any(|c| c == "USDT"));
 
-        let resp: BybitResponse = serde_json::from_str(&txt)?;
+        let transfer_id = "42c0cfb0-6bca-c242-bc76-4e6df6cbcb16";
+        let receipt = bybit.inter_transfer(transfer_id, "USDT", 1.0, AccountType::FUND, AccountType::UNIFIED).await.unwrap();
+        dbg!(&receipt);
 
-        if resp.ret_code != 0 {
-            bail!("bybit err resp: {}", resp.ret_msg);
-        }
+        let status = bybit.get_transfer_status(transfer_id, false).await.unwrap();
+        dbg!(status, status.is_final());
 
-        let position_list = resp.result
-        .get("list")
-        .and_then(Value::as_array)
-        .context("Failed to extract osition list from response")?;
+        let status = bybit.wait_for_transfer(transfer_id, false, Duration::from_secs(30)).await.unwrap();
+        assert!(status.is_final());
+    }
 
-        let mut map: HashMap<String, PositionInfo> = HashMap::default();
+    #[tokio::test]
+    pub async fn test_transfer_status_filter() {
+        let bybit = Bybit::new(None, None, None).unwrap();
 
-        for position in position_list.iter() {
-            let info: PositionInfo = serde_json::from_value(position.clone())?;
-            map.insert(info.symbol.clone(), info);
-        }
+        // STATUS_UNKNOWN only shows up in responses, bybit doesn't accept it as a filter
+        let err = bybit.get_inter_transfers(None, Some(TransferStatus::StatusUnknown), None, None).await.unwrap_err();
+        assert!(err.to_string().contains("StatusUnknown"));
 
-        Ok(map)
+        let err = bybit.get_universal_transfers(None, Some(TransferStatus::StatusUnknown), None, None).await.unwrap_err();
+        assert!(err.to_string().contains("StatusUnknown"));
     }
-}
 
+    #[tokio::test]
+    pub async fn test_preflight() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
 
-#[cfg(test)]
-mod tests {
-    use std::env;
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-    use utils::unlock_keys;
-    use super::*;
+        let report = bybit.preflight(&PreflightPolicy::new()).await.unwrap();
+        dbg!(&report);
+        report.ensure_ready().unwrap();
+    }
 
     #[tokio::test]
-    pub async fn test_cancel_order() {
-        let (api_key, api_secret) = unlock_keys().unwrap();
+    pub async fn test_create_sub_member_username() {
+        let bybit = Bybit::new(None, None, None).unwrap();
 
-        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
-        let order_id = String::from("xxxxx");
-        bybit.cancel_order(Category::Linear, "ETHUSDT", OrderId::OrderID(order_id)).await.unwrap();
+        // rejected locally, before any request is signed
+        for username in ["strategyonly", "12345678", "abc12", "strategy_0001"] {
+            let err = bybit.create_sub_member(username, SubMemberType::Normal, None).await.unwrap_err();
+            assert!(err.to_string().contains("username"), "{username}: {err}");
+        }
+
+        let err = bybit.create_sub_member("strategy0001", SubMemberType::Normal, None).await.unwrap_err();
+        assert!(!err.to_string().contains("username"));
     }
 
     #[tokio::test]
-    pub async fn test_cancel_all_orders() {
+    pub async fn test_get_sub_members() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
-        bybit.cancel_all_orders(Category::Linear, "ETHUSDT").await.unwrap();
+
+        let members = bybit.get_sub_members().await.unwrap();
+        dbg!(&members);
+
+        let settings = ApiKeySettings::new("strategy")
+            .permission(ApiPermission::ContractOrder)
+            .permission(ApiPermission::ContractPosition);
+        dbg!(settings.into_json().unwrap());
     }
 
     #[tokio::test]
-    pub async fn test_create_limit_order() {
+    pub async fn test_convert_quote() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
-        let proxy_url = env::var("proxy_url").ok();
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let bybit = Bybit::new(Some(api_key), Some(api_secret), proxy_url).unwrap();
-        let resp = bybit.create_order(Category::Linear, "ETHUSDT", TradeDirection::Buy, OrderType::Limit, 0.1, Some(3000.21), Some(TimeInForce::GTC)).await.unwrap();
-        dbg!(resp);
+        let coins = bybit.get_convert_coins(ConvertAccountType::Unified, None, ConvertSide::From).await.unwrap();
+        dbg!(coins.len());
+
+        // quote only, never executed
+        let quote = bybit.request_convert_quote(ConvertAccountType::Unified, "USDC", "USDT", 10.0).await.unwrap();
+        dbg!(&quote);
+
+        let history = bybit.get_convert_history(&[ConvertAccountType::Unified, ConvertAccountType::Funding]).await.unwrap();
+        dbg!(history.len());
     }
 
     #[tokio::test]
-    pub async fn test_create_market_order() {
+    pub async fn test_get_coin_info_and_deposit_address() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
-        bybit.create_order(Category::Linear, "ETHUSDT", TradeDirection::Buy, OrderType::Market, 0.01, None, None).await.unwrap();
+
+        let chain = bybit.get_chain_info("USDT", "ETH").await.unwrap();
+        dbg!(&chain);
+
+        let addresses = bybit.get_deposit_addresses("USDT", Some("ETH")).await.unwrap();
+        dbg!(addresses);
     }
 
     #[tokio::test]
-    pub async fn test_create_batch_market_orders() {
+    pub async fn test_get_deposit_and_withdrawal_records() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let mut new_orders = vec![];
-
-        new_orders.push(NewOrder {
-            symbol: String::from("BLASTUSDT"),
-            side: TradeDirection::Buy,
-            order_type: OrderType::Market,
-            price: None,
-            qty: 1000.0,
-            time_in_force: None
-        });
+        let start_time = get_timestamp() as u64 - 90 * 24 * 60 * 60 * 1000;
+        let deposits = bybit.get_deposit_records(None, Some(start_time), None).await.unwrap();
+        dbg!(deposits.len());
 
-        new_orders.push(NewOrder {
-            symbol: String::from("BLASTUSDT"),
-            side: TradeDirection::Buy,
-            order_type: OrderType::Market,
-            price: None,
-            qty: 1200.0,
-            time_in_force: None
-        });
+        let withdrawals = bybit.get_withdrawal_records(None, Some(WithdrawType::All), Some(start_time), None).await.unwrap();
+        dbg!(withdrawals.len());
+    }
 
-        let receipts = bybit.batch_create_order(Category::Linear,new_orders).await.unwrap();
-        dbg!(receipts);
+    #[tokio::test]
+    pub async fn test_withdraw_policy_gate() {
+        let bybit = Bybit::new(None, None, None).unwrap();
+        let address = "0x99ced335e1d6bc26e8c2d0f3c6c6c0a1c3f8e3b1";
+        let request = WithdrawRequest::new("USDT", "ETH", address, 10.0);
+
+        // refused before any request is signed
+        assert!(bybit.withdraw(&request).await.is_err());
+
+        bybit.set_withdrawal_policy(WithdrawalPolicy::new().daily_limit("USDT", 100.0)).unwrap();
+        assert!(bybit.withdraw(&request).await.is_err());
+
+        let policy = WithdrawalPolicy::new()
+            .allow("USDT", "ETH", address)
+            .daily_limit("USDT", 100.0)
+            .confirm_with(|_| false);
+        bybit.set_withdrawal_policy(policy).unwrap();
+        let err = bybit.withdraw(&request).await.unwrap_err();
+        dbg!(err);
+
+        // no secret, so signing fails after the amount was counted against the limit
+        let policy = WithdrawalPolicy::new().allow("USDT", "ETH", address).daily_limit("USDT", 15.0);
+        bybit.set_withdrawal_policy(policy.clone()).unwrap();
+        assert!(bybit.withdraw(&request).await.is_err());
+
+        // re-applying the policy must not reset today's usage
+        bybit.set_withdrawal_policy(policy).unwrap();
+        let err = bybit.withdraw(&request).await.unwrap_err();
+        assert!(err.to_string().contains("daily limit"));
     }
 
     #[tokio::test]
-    pub async fn test_get_orders() {
+    pub async fn test_get_wallet_balance() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
-        // market order d3075c7f-0cae-4bc0-9ace-e9b5d9055326
-        // limit order 1f6b52d0-0d38-4c44-a558-0d2619b58061
-        let order_id = String::from("d3075c7f-0cae-4bc0-9ace-e9b5d9055326");
-        let orders = bybit.get_orders(Category::Linear, "ETHUSDT",Some(OrderId::OrderID(order_id))).await.unwrap();
-        dbg!(orders);
+
+        let balance = bybit.get_wallet_balance(AccountType::UNIFIED, None).await.unwrap();
+        dbg!(balance);
     }
 
     #[tokio::test]
-    pub async fn test_get_wallet_balance() {
+    pub async fn test_get_total_holdings() {
         let (api_key, api_secret) = unlock_keys().unwrap();
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let balance = bybit.get_wallet_balance(AccountType::UNIFIED, None).await.unwrap();
-        dbg!(balance);
+        let holdings = bybit.get_total_holdings(&[AccountType::UNIFIED, AccountType::FUND]).await.unwrap();
+        dbg!(holdings);
     }
 
     #[tokio::test]
@@ -802,10 +3531,20 @@ mod tests {
 
         let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
 
-        let map = bybit.get_position_info(Category::Linear, Some("USDT")).await.unwrap();
+        let map = bybit.get_position_info(Category::Linear, Some(PositionScope::SettleCoin(String::from("USDT")))).await.unwrap();
         dbg!(map);
     }
 
+    #[tokio::test]
+    pub async fn test_get_all_positions() {
+        let (api_key, api_secret) = unlock_keys().unwrap();
+
+        let bybit = Bybit::new(Some(api_key), Some(api_secret), None).unwrap();
+
+        let positions = bybit.get_all_positions().await.unwrap();
+        dbg!(positions);
+    }
+
     #[tokio::test]
     pub async fn test_get_inverse_position_info() {
         let (api_key, api_secret) = unlock_keys().unwrap();
@@ -1,8 +1,12 @@
-use anyhow::ensure;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use crate::utils::{parse_string_to_f64, parse_string_to_option_f64};
-use crate::{OrderType, TimeInForce, TradeDirection};
+use crate::utils::{parse_empty_string_to_none, parse_string_to_bool, parse_string_to_f64, parse_string_to_f64_or_zero, parse_string_to_option_f64, parse_string_to_u64, parse_string_to_u64_or_zero, parse_zero_to_none};
+use crate::{AccountType, ApiPermission, ForceChain, Category, ConvertAccountType, ConvertStatus, DepositStatus, ExecType, InternalDepositStatus, MarginMode, MarketUnit, OrderFilter, OrderType, PositionIdx, PositionSide, SlippageTolerance, SmpType, StopOrderType, SubMemberStatus, SubMemberType, TimeInForce, TpslMode, TradeDirection, TransactionType, TransferStatus, TriggerBy, TriggerDirection, WithdrawAccountType, WithdrawStatus};
 
 
 // {"retCode":0,"retMsg":"OK","result":{"orderId":"xxxx","orderLinkId":""},"retExtInfo":{},"time":1722030653718}
@@ -27,6 +31,14 @@ pub struct CreateOrderResponse {
     pub order_link_id: String
 }
 
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CancelledOrder {
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    #[serde(rename = "orderLinkId")]
+    pub order_link_id: String
+}
+
 #[derive(Deserialize, Debug)]
 pub struct CreateBatchOrderResponse {
     pub list: Vec<CreateBatchOrderItemResponse>
@@ -84,7 +96,7 @@ pub struct Order {
     #[serde(rename = "placeType")]
     pub place_type: String,
     #[serde(rename = "positionIdx")]
-    pub position_idx: i32,
+    pub position_idx: PositionIdx,
     #[serde(deserialize_with = "parse_string_to_f64")]
     pub price: f64,
     #[serde(deserialize_with = "parse_string_to_f64")]
@@ -94,219 +106,1553 @@ pub struct Order {
     #[serde(rename = "rejectReason")]
     pub reject_reason: String,
     pub side: TradeDirection,
-    #[serde(rename = "slLimitPrice")]
-    pub sl_limit_price: String,
-    #[serde(rename = "slTriggerBy")]
-    pub sl_trigger_by: String,
+    #[serde(rename = "slLimitPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub sl_limit_price: Option<f64>,
+    #[serde(rename = "slTriggerBy", deserialize_with = "parse_empty_string_to_none")]
+    pub sl_trigger_by: Option<TriggerBy>,
     #[serde(rename = "smpGroup")]
     pub smp_group: i32,
     #[serde(rename = "smpOrderId")]
     pub smp_order_id: String,
     #[serde(rename = "smpType")]
     pub smp_type: String,
-    #[serde(rename = "stopLoss")]
-    pub stop_loss: String,
-    #[serde(rename = "stopOrderType")]
-    pub stop_order_type: String,
+    #[serde(rename = "stopLoss", deserialize_with = "parse_string_to_option_f64")]
+    pub stop_loss: Option<f64>,
+    #[serde(rename = "stopOrderType", deserialize_with = "parse_empty_string_to_none")]
+    pub stop_order_type: Option<StopOrderType>,
     pub symbol: String,
-    #[serde(rename = "takeProfit")]
-    pub take_profit: String,
+    #[serde(rename = "takeProfit", deserialize_with = "parse_string_to_option_f64")]
+    pub take_profit: Option<f64>,
     #[serde(rename = "timeInForce")]
     pub time_in_force: String,
-    #[serde(rename = "tpLimitPrice")]
-    pub tp_limit_price: String,
-    #[serde(rename = "tpTriggerBy")]
-    pub tp_trigger_by: String,
-    #[serde(rename = "tpslMode")]
-    pub tpsl_mode: String,
-    #[serde(rename = "triggerBy")]
-    pub trigger_by: String,
-    #[serde(rename = "triggerDirection")]
-    pub trigger_direction: i32,
-    #[serde(rename = "triggerPrice")]
-    pub trigger_price: String,
+    #[serde(rename = "tpLimitPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub tp_limit_price: Option<f64>,
+    #[serde(rename = "tpTriggerBy", deserialize_with = "parse_empty_string_to_none")]
+    pub tp_trigger_by: Option<TriggerBy>,
+    #[serde(rename = "tpslMode", deserialize_with = "parse_empty_string_to_none")]
+    pub tpsl_mode: Option<TpslMode>,
+    #[serde(rename = "triggerBy", deserialize_with = "parse_empty_string_to_none")]
+    pub trigger_by: Option<TriggerBy>,
+    #[serde(rename = "triggerDirection", deserialize_with = "parse_zero_to_none")]
+    pub trigger_direction: Option<TriggerDirection>,
+    #[serde(rename = "triggerPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub trigger_price: Option<f64>,
+    #[serde(rename = "updatedTime")]
+    pub updated_time: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/order/execution
+#[derive(Debug, Deserialize, Clone)]
+pub struct Execution {
+    pub symbol: String,
+    #[serde(rename = "execId")]
+    pub exec_id: String,
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    #[serde(rename = "orderLinkId")]
+    pub order_link_id: String,
+    pub side: TradeDirection,
+    #[serde(rename = "orderType")]
+    pub order_type: String,
+    #[serde(rename = "stopOrderType", deserialize_with = "parse_empty_string_to_none")]
+    pub stop_order_type: Option<StopOrderType>,
+    #[serde(rename = "orderPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub order_price: Option<f64>,
+    #[serde(rename = "orderQty", deserialize_with = "parse_string_to_option_f64")]
+    pub order_qty: Option<f64>,
+    #[serde(rename = "leavesQty", deserialize_with = "parse_string_to_option_f64")]
+    pub leaves_qty: Option<f64>,
+    #[serde(rename = "execType")]
+    pub exec_type: ExecType,
+    #[serde(rename = "execPrice", deserialize_with = "parse_string_to_f64")]
+    pub exec_price: f64,
+    #[serde(rename = "execQty", deserialize_with = "parse_string_to_f64")]
+    pub exec_qty: f64,
+    #[serde(rename = "execValue", deserialize_with = "parse_string_to_f64")]
+    pub exec_value: f64,
+    #[serde(rename = "execFee", deserialize_with = "parse_string_to_f64")]
+    pub exec_fee: f64,
+    #[serde(rename = "feeRate", deserialize_with = "parse_string_to_option_f64")]
+    pub fee_rate: Option<f64>,
+    #[serde(rename = "feeCurrency", default)]
+    pub fee_currency: String,
+    #[serde(rename = "execTime")]
+    pub exec_time: String,
+    #[serde(rename = "isMaker")]
+    pub is_maker: bool,
+    #[serde(rename = "markPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub mark_price: Option<f64>,
+    #[serde(rename = "closedSize", deserialize_with = "parse_string_to_option_f64")]
+    pub closed_size: Option<f64>,
+    #[serde(rename = "blockTradeId")]
+    pub block_trade_id: String,
+    #[serde(default)]
+    pub seq: i64,
+
+    // options only, empty for other categories
+    #[serde(rename = "indexPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub index_price: Option<f64>,
+    #[serde(rename = "underlyingPrice", deserialize_with = "parse_string_to_option_f64")]
+    pub underlying_price: Option<f64>,
+    #[serde(rename = "tradeIv", deserialize_with = "parse_string_to_option_f64")]
+    pub trade_iv: Option<f64>,
+    #[serde(rename = "markIv", deserialize_with = "parse_string_to_option_f64")]
+    pub mark_iv: Option<f64>,
+}
+
+// https://bybit-exchange.github.io/docs/v5/position/close-pnl
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClosedPnl {
+    pub symbol: String,
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    pub side: TradeDirection,
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub qty: f64,
+    #[serde(rename = "orderPrice", deserialize_with = "parse_string_to_f64")]
+    pub order_price: f64,
+    #[serde(rename = "orderType")]
+    pub order_type: OrderType,
+    #[serde(rename = "execType")]
+    pub exec_type: ExecType,
+    #[serde(rename = "closedSize", deserialize_with = "parse_string_to_f64")]
+    pub closed_size: f64,
+    #[serde(rename = "cumEntryValue", deserialize_with = "parse_string_to_f64")]
+    pub cum_entry_value: f64,
+    #[serde(rename = "avgEntryPrice", deserialize_with = "parse_string_to_f64")]
+    pub avg_entry_price: f64,
+    #[serde(rename = "cumExitValue", deserialize_with = "parse_string_to_f64")]
+    pub cum_exit_value: f64,
+    #[serde(rename = "avgExitPrice", deserialize_with = "parse_string_to_f64")]
+    pub avg_exit_price: f64,
+    #[serde(rename = "closedPnl", deserialize_with = "parse_string_to_f64")]
+    pub closed_pnl: f64,
+    #[serde(rename = "fillCount", deserialize_with = "parse_string_to_f64")]
+    pub fill_count: f64,
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub leverage: f64,
+    #[serde(rename = "openFee", default, deserialize_with = "parse_string_to_option_f64")]
+    pub open_fee: Option<f64>,
+    #[serde(rename = "closeFee", default, deserialize_with = "parse_string_to_option_f64")]
+    pub close_fee: Option<f64>,
+    #[serde(rename = "createdTime")]
+    pub created_time: String,
+    #[serde(rename = "updatedTime")]
+    pub updated_time: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/fee-rate
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct FeeRate {
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(rename = "baseCoin", default)]
+    pub base_coin: String,
+    #[serde(rename = "takerFeeRate", deserialize_with = "parse_string_to_f64")]
+    pub taker_fee_rate: f64,
+    #[serde(rename = "makerFeeRate", deserialize_with = "parse_string_to_f64")]
+    pub maker_fee_rate: f64,
+}
+
+impl FeeRate {
+    // options are quoted per base coin with an empty symbol
+    pub fn key(&self) -> &str {
+        if self.symbol.is_empty() {
+            &self.base_coin
+        } else {
+            &self.symbol
+        }
+    }
+
+    pub fn rate(&self, is_maker: bool) -> f64 {
+        if is_maker {
+            self.maker_fee_rate
+        } else {
+            self.taker_fee_rate
+        }
+    }
+
+    /// Fee on `notional`; negative for maker rebates.
+    pub fn expected_fee(&self, notional: f64, is_maker: bool) -> f64 {
+        notional * self.rate(is_maker)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderCostEstimate {
+    // in quote coin (settle coin for inverse)
+    pub notional: f64,
+    pub fee: f64,
+    pub fee_rate: f64,
+    pub is_maker: bool,
+}
+
+impl OrderCostEstimate {
+    /// Linear, inverse and spot only; options are not supported. `maker_op` says whether the order
+    /// is expected to rest on the book. When None, only PostOnly/RPI orders are costed as maker, so
+    /// a GTC or IOC limit order is estimated at the taker rate.
+    pub fn new(category: Category, order: &OrderRequest, rate: &FeeRate, reference_price: Option<f64>, maker_op: Option<bool>) -> anyhow::Result<Self> {
+        ensure!(!category.eq(&Category::Option), "cost estimation is not supported for options");
+        ensure!(!(order.order_type == OrderType::Market && maker_op == Some(true)), "a market order cannot be maker");
+
+        let price = order.price
+            .or(reference_price)
+            .ok_or_else(|| anyhow::anyhow!("estimate needs a price or reference_price"))?;
+        ensure!(price > 0.0, "price must be positive");
+
+        let notional = match category {
+            // inverse qty is in USD contracts, the fee is paid in the coin
+            Category::Inverse => order.qty / price,
+            _ if order.market_unit.eq(&Some(MarketUnit::QuoteCoin)) => order.qty,
+            _ => order.qty * price,
+        };
+
+        let is_maker = maker_op.unwrap_or(matches!(order.time_in_force, Some(TimeInForce::PostOnly) | Some(TimeInForce::RPI)));
+        let fee_rate = rate.rate(is_maker);
+
+        Ok(Self {
+            notional,
+            fee: notional * fee_rate,
+            fee_rate,
+            is_maker,
+        })
+    }
+
+    pub fn total(&self) -> f64 {
+        self.notional + self.fee
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/transaction-log
+#[derive(Debug, Deserialize, Clone)]
+pub struct TransactionLog {
+    pub id: String,
+    pub symbol: String,
+    pub category: String,
+    pub side: PositionSide,
+    #[serde(rename = "transactionTime")]
+    pub transaction_time: String,
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    #[serde(deserialize_with = "parse_string_to_f64_or_zero")]
+    pub qty: f64,
+    #[serde(deserialize_with = "parse_string_to_f64_or_zero")]
+    pub size: f64,
+    pub currency: String,
+    #[serde(rename = "tradePrice", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub trade_price: f64,
+    // positive is received, negative paid
+    #[serde(deserialize_with = "parse_string_to_f64_or_zero")]
+    pub funding: f64,
+    #[serde(deserialize_with = "parse_string_to_f64_or_zero")]
+    pub fee: f64,
+    #[serde(rename = "cashFlow", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub cash_flow: f64,
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub change: f64,
+    #[serde(rename = "cashBalance", deserialize_with = "parse_string_to_f64")]
+    pub cash_balance: f64,
+    #[serde(rename = "feeRate", deserialize_with = "parse_string_to_option_f64")]
+    pub fee_rate: Option<f64>,
+    #[serde(rename = "bonusChange", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub bonus_change: f64,
+    #[serde(rename = "tradeId")]
+    pub trade_id: String,
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    #[serde(rename = "orderLinkId")]
+    pub order_link_id: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/account-info
+#[derive(Debug, Deserialize, Clone)]
+pub struct AccountSettings {
+    // 1 classic, 3 UTA1.0, 4 UTA1.0 pro, 5 UTA2.0, 6 UTA2.0 pro
+    #[serde(rename = "unifiedMarginStatus")]
+    pub unified_margin_status: i32,
+    #[serde(rename = "marginMode")]
+    pub margin_mode: MarginMode,
+    #[serde(rename = "isMasterTrader")]
+    pub is_master_trader: bool,
+    #[serde(rename = "spotHedgingStatus", deserialize_with = "parse_string_to_bool")]
+    pub spot_hedging_status: bool,
+    #[serde(rename = "dcpStatus", deserialize_with = "parse_string_to_bool")]
+    pub dcp_status: bool,
+    #[serde(rename = "timeWindow")]
+    pub time_window: u64,
+    #[serde(rename = "smpGroup")]
+    pub smp_group: i32,
     #[serde(rename = "updatedTime")]
     pub updated_time: String,
 }
 
+impl AccountSettings {
+    pub fn is_unified(&self) -> bool {
+        self.unified_margin_status >= 3
+    }
+
+    pub fn is_uta2(&self) -> bool {
+        self.unified_margin_status >= 5
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/wallet-balance
+// account-level margin fields are empty in isolated margin mode, hence the Options
 #[derive(Debug, Deserialize)]
 pub struct AccountInfo {
     #[serde(rename = "totalEquity", deserialize_with = "parse_string_to_f64")]
     pub total_equity: f64,
-    //#[serde(rename = "accountIMRate", deserialize_with = "parse_string_to_f64")]
-    //pub account_im_rate: f64,
-    #[serde(rename = "totalMarginBalance")]
-    pub total_margin_balance: String,
-    #[serde(rename = "totalInitialMargin")]
-    pub total_initial_margin: String,
+    #[serde(rename = "accountIMRate", default, deserialize_with = "parse_string_to_option_f64")]
+    pub account_im_rate: Option<f64>,
+    #[serde(rename = "totalMarginBalance", default, deserialize_with = "parse_string_to_option_f64")]
+    pub total_margin_balance: Option<f64>,
+    #[serde(rename = "totalInitialMargin", default, deserialize_with = "parse_string_to_option_f64")]
+    pub total_initial_margin: Option<f64>,
     #[serde(rename = "accountType")]
     pub account_type: String,
-    #[serde(rename = "totalAvailableBalance")]
-    pub total_available_balance: String,
-    //#[serde(rename = "accountMMRate", deserialize_with = "parse_string_to_f64")]
-    //pub account_mm_rate: f64,
-    #[serde(rename = "totalPerpUPL", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "totalAvailableBalance", default, deserialize_with = "parse_string_to_option_f64")]
+    pub total_available_balance: Option<f64>,
+    #[serde(rename = "accountMMRate", default, deserialize_with = "parse_string_to_option_f64")]
+    pub account_mm_rate: Option<f64>,
+    #[serde(rename = "totalPerpUPL", default, deserialize_with = "parse_string_to_f64")]
     pub total_perp_upl: f64,
     #[serde(rename = "totalWalletBalance", deserialize_with = "parse_string_to_f64")]
     pub total_wallet_balance: f64,
-    //#[serde(rename = "accountLTV", deserialize_with = "parse_string_to_f64")]
-    //pub account_ltv: f64,
-    #[serde(rename = "totalMaintenanceMargin")]
-    pub total_maintenance_margin: String,
+    #[serde(rename = "accountLTV", default, deserialize_with = "parse_string_to_option_f64")]
+    pub account_ltv: Option<f64>,
+    #[serde(rename = "totalMaintenanceMargin", default, deserialize_with = "parse_string_to_option_f64")]
+    pub total_maintenance_margin: Option<f64>,
     pub coin: Vec<CoinInfo>,
 }
 
+impl AccountInfo {
+    /// Maintenance margin over margin balance; the account is liquidated at 1.0.
+    pub fn margin_ratio(&self) -> Option<f64> {
+        self.account_mm_rate.or_else(|| {
+            let balance = self.total_margin_balance?;
+            (balance > 0.0).then(|| self.total_maintenance_margin.unwrap_or(0.0) / balance)
+        })
+    }
+
+    /// Initial margin over margin balance; no new positions can be opened at 1.0.
+    pub fn initial_margin_ratio(&self) -> Option<f64> {
+        self.account_im_rate.or_else(|| {
+            let balance = self.total_margin_balance?;
+            (balance > 0.0).then(|| self.total_initial_margin.unwrap_or(0.0) / balance)
+        })
+    }
+
+    /// Margin balance not tied up as initial margin, in USD.
+    pub fn free_collateral(&self) -> Option<f64> {
+        self.total_available_balance.or_else(|| {
+            Some(self.total_margin_balance? - self.total_initial_margin.unwrap_or(0.0))
+        })
+    }
+
+    /// Margin balance that can be lost before maintenance margin is breached, in USD.
+    pub fn liquidation_buffer(&self) -> Option<f64> {
+        Some(self.total_margin_balance? - self.total_maintenance_margin.unwrap_or(0.0))
+    }
+
+    pub fn coin(&self, coin: &str) -> Option<&CoinInfo> {
+        self.coin.iter().find(|c| c.coin == coin)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CoinInfo {
-    #[serde(rename = "availableToBorrow")]
-    pub available_to_borrow: String,
-    #[serde(deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "availableToBorrow", default, deserialize_with = "parse_string_to_option_f64")]
+    pub available_to_borrow: Option<f64>,
+    #[serde(default, deserialize_with = "parse_string_to_f64_or_zero")]
     pub bonus: f64,
-    //#[serde(rename = "accruedInterest", deserialize_with = "parse_string_to_f64")]
-    #[serde(rename = "accruedInterest")]
-    pub accrued_interest: String,
-    #[serde(rename = "availableToWithdraw")]
-    pub available_to_withdraw: String,
-    #[serde(rename = "totalOrderIM", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "accruedInterest", default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub accrued_interest: f64,
+    #[serde(rename = "availableToWithdraw", default, deserialize_with = "parse_string_to_option_f64")]
+    pub available_to_withdraw: Option<f64>,
+    #[serde(rename = "totalOrderIM", default, deserialize_with = "parse_string_to_f64_or_zero")]
     pub total_order_im: f64,
     #[serde(deserialize_with = "parse_string_to_f64")]
     pub equity: f64,
-    #[serde(rename = "totalPositionMM", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "totalPositionMM", default, deserialize_with = "parse_string_to_f64_or_zero")]
     pub total_position_mm: f64,
-    #[serde(rename = "usdValue", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "usdValue", default, deserialize_with = "parse_string_to_f64")]
     pub usd_value: f64,
-    #[serde(rename = "unrealisedPnl", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "unrealisedPnl", default, deserialize_with = "parse_string_to_f64")]
     pub unrealised_pnl: f64,
-    #[serde(rename = "collateralSwitch")]
+    #[serde(rename = "collateralSwitch", default)]
     pub collateral_switch: bool,
-    #[serde(rename = "spotHedgingQty", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "spotHedgingQty", default, deserialize_with = "parse_string_to_f64_or_zero")]
     pub spot_hedging_qty: f64,
-    #[serde(rename = "borrowAmount")]
-    pub borrow_amount: String,
-    #[serde(rename = "totalPositionIM", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "borrowAmount", default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub borrow_amount: f64,
+    #[serde(rename = "totalPositionIM", default, deserialize_with = "parse_string_to_f64_or_zero")]
     pub total_position_im: f64,
     #[serde(rename = "walletBalance", deserialize_with = "parse_string_to_f64")]
     pub wallet_balance: f64,
-    #[serde(rename = "cumRealisedPnl", deserialize_with = "parse_string_to_f64")]
+    #[serde(rename = "cumRealisedPnl", default, deserialize_with = "parse_string_to_f64")]
     pub cum_realised_pnl: f64,
-    #[serde(deserialize_with = "parse_string_to_f64")]
+    #[serde(default, deserialize_with = "parse_string_to_f64")]
     pub locked: f64,
-    #[serde(rename = "marginCollateral")]
+    #[serde(rename = "marginCollateral", default)]
     pub margin_collateral: bool,
     pub coin: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ContractInfo {
-    pub symbol: String,
-    #[serde(rename = "contractType")]
-    pub contract_type: String,
-    pub status: String,
+impl CoinInfo {
+    /// Wallet balance not locked by orders or margin, net of borrowings.
+    pub fn free_balance(&self) -> f64 {
+        self.wallet_balance - self.locked - self.total_order_im - self.total_position_im - self.borrow_amount
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/balance/all-balance
+#[derive(Debug, Deserialize, Clone)]
+pub struct CoinBalance {
+    pub coin: String,
+    #[serde(rename = "walletBalance", deserialize_with = "parse_string_to_f64")]
+    pub wallet_balance: f64,
+    #[serde(rename = "transferBalance", default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub transfer_balance: f64,
+    #[serde(default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub bonus: f64,
+    #[serde(rename = "transferSafeAmount", default, deserialize_with = "parse_string_to_option_f64")]
+    pub transfer_safe_amount: Option<f64>,
+}
+
+// one coin's balance summed across wallets
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinHolding {
+    pub coin: String,
+    pub total: f64,
+    pub by_account: HashMap<AccountType, f64>,
+}
+
+impl CoinHolding {
+    pub fn new(coin: &str) -> Self {
+        Self {
+            coin: coin.to_string(),
+            total: 0.0,
+            by_account: HashMap::default(),
+        }
+    }
+
+    pub fn add(&mut self, account_type: AccountType, amount: f64) {
+        self.total += amount;
+        *self.by_account.entry(account_type).or_default() += amount;
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/collateral-info
+#[derive(Debug, Deserialize, Clone)]
+pub struct CollateralInfo {
+    pub currency: String,
+    #[serde(rename = "hourlyBorrowRate", deserialize_with = "parse_string_to_f64")]
+    pub hourly_borrow_rate: f64,
+    #[serde(rename = "maxBorrowingAmount", deserialize_with = "parse_string_to_f64")]
+    pub max_borrowing_amount: f64,
+    #[serde(rename = "freeBorrowingLimit", default, deserialize_with = "parse_string_to_f64")]
+    pub free_borrowing_limit: f64,
+    #[serde(rename = "freeBorrowAmount", default, deserialize_with = "parse_string_to_f64")]
+    pub free_borrow_amount: f64,
+    #[serde(rename = "borrowAmount", deserialize_with = "parse_string_to_f64")]
+    pub borrow_amount: f64,
+    #[serde(rename = "otherBorrowAmount", default, deserialize_with = "parse_string_to_f64")]
+    pub other_borrow_amount: f64,
+    #[serde(rename = "availableToBorrow", deserialize_with = "parse_string_to_f64")]
+    pub available_to_borrow: f64,
+    pub borrowable: bool,
+    #[serde(rename = "borrowUsageRate", default, deserialize_with = "parse_string_to_f64")]
+    pub borrow_usage_rate: f64,
+    #[serde(rename = "marginCollateral")]
+    pub margin_collateral: bool,
+    #[serde(rename = "collateralSwitch")]
+    pub collateral_switch: bool,
+    #[serde(rename = "collateralRatio", deserialize_with = "parse_string_to_f64")]
+    pub collateral_ratio: f64,
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/upgrade-unified-account
+#[derive(Debug, Deserialize, Clone)]
+pub struct UpgradeStatus {
+    // FAIL, PROCESS or SUCCESS
+    #[serde(rename = "unifiedUpdateStatus")]
+    pub unified_update_status: String,
+    #[serde(rename = "unifiedUpdateMsg", default)]
+    pub unified_update_msg: Value,
+}
+
+impl UpgradeStatus {
+    /// Reasons bybit gives for a failed upgrade.
+    pub fn messages(&self) -> Vec<String> {
+        self.unified_update_msg
+            .get("msg")
+            .and_then(Value::as_array)
+            .map(|list| list.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default()
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/set-mmp
+// MMP freezes quoting on `base_coin` once the traded qty or delta within `window_ms`
+// exceeds the limits, for `frozen_period_ms` (0 = until reset_mmp)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MmpConfig {
+    pub base_coin: String,
+    pub window_ms: u64,
+    pub frozen_period_ms: u64,
+    pub qty_limit: f64,
+    pub delta_limit: f64,
+}
+
+impl MmpConfig {
+    pub fn new(base_coin: &str, window_ms: u64, frozen_period_ms: u64, qty_limit: f64, delta_limit: f64) -> Self {
+        Self {
+            base_coin: base_coin.to_string(),
+            window_ms,
+            frozen_period_ms,
+            qty_limit,
+            delta_limit,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(!self.base_coin.is_empty(), "base_coin is empty");
+        ensure!(self.window_ms > 0, "window_ms must be positive");
+        ensure!(self.qty_limit > 0.0, "qty_limit must be positive");
+        ensure!(self.delta_limit > 0.0, "delta_limit must be positive");
+        Ok(())
+    }
+
+    pub fn into_json(&self) -> anyhow::Result<Value> {
+        self.validate()?;
+
+        Ok(json!({
+            "baseCoin": self.base_coin,
+            "window": self.window_ms.to_string(),
+            "frozenPeriod": self.frozen_period_ms.to_string(),
+            "qtyLimit": self.qty_limit.to_string(),
+            "deltaLimit": self.delta_limit.to_string(),
+        }))
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/account/get-mmp-state
+#[derive(Debug, Deserialize, Clone)]
+pub struct MmpState {
     #[serde(rename = "baseCoin")]
     pub base_coin: String,
-    #[serde(rename = "quoteCoin")]
-    pub quote_coin: String,
-    #[serde(rename = "launchTime")]
-    pub launch_time: String,
-    #[serde(rename = "deliveryTime")]
-    pub delivery_time: String,
-    #[serde(rename = "deliveryFeeRate")]
-    pub delivery_fee_rate: String,
-    #[serde(rename = "priceScale")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub price_scale: f64,
-    #[serde(rename = "leverageFilter")]
-    pub leverage_filter: LeverageFilter,
-    #[serde(rename = "priceFilter")]
-    pub price_filter: PriceFilter,
-    #[serde(rename = "lotSizeFilter")]
-    pub lot_size_filter: LotSizeFilter,
-    #[serde(rename = "unifiedMarginTrade")]
-    pub unified_margin_trade: bool,
-    #[serde(rename = "fundingInterval")]
-    pub funding_interval: i32,
-    #[serde(rename = "settleCoin")]
-    pub settle_coin: String,
-    #[serde(rename = "copyTrading")]
-    pub copy_trading: String,
-    #[serde(rename = "upperFundingRate")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub upper_funding_rate: f64,
-    #[serde(rename = "lowerFundingRate")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub lower_funding_rate: f64,
+    #[serde(rename = "mmpEnabled")]
+    pub mmp_enabled: bool,
+    #[serde(deserialize_with = "parse_string_to_u64")]
+    pub window: u64,
+    #[serde(rename = "frozenPeriod", deserialize_with = "parse_string_to_u64")]
+    pub frozen_period: u64,
+    #[serde(rename = "qtyLimit", deserialize_with = "parse_string_to_f64")]
+    pub qty_limit: f64,
+    #[serde(rename = "deltaLimit", deserialize_with = "parse_string_to_f64")]
+    pub delta_limit: f64,
+    #[serde(rename = "mmpFrozenUntil", deserialize_with = "parse_string_to_u64")]
+    pub mmp_frozen_until: u64,
+    #[serde(rename = "mmpFrozen")]
+    pub mmp_frozen: bool,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct LeverageFilter {
-    #[serde(rename = "minLeverage")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub min_leverage: f64,
-    #[serde(rename = "maxLeverage")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub max_leverage: f64,
-    #[serde(rename = "leverageStep")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub leverage_step: f64,
+impl MmpState {
+    pub fn config(&self) -> MmpConfig {
+        MmpConfig::new(&self.base_coin, self.window, self.frozen_period, self.qty_limit, self.delta_limit)
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PriceFilter {
-    #[serde(rename = "minPrice")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub min_price: f64,
-    #[serde(rename = "maxPrice")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub max_price: f64,
-    #[serde(rename = "tickSize")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub tick_size: f64,
+// https://bybit-exchange.github.io/docs/v5/asset/transfer/create-inter-transfer
+#[derive(Debug, Deserialize, Clone)]
+pub struct TransferReceipt {
+    #[serde(rename = "transferId")]
+    pub transfer_id: String,
+    // older responses only carry the id, the transfer calls then look the status up
+    #[serde(default = "unknown_transfer")]
+    pub status: TransferStatus,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct LotSizeFilter {
-    #[serde(rename = "maxOrderQty")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub max_order_qty: f64,
-    #[serde(rename = "minOrderQty")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub min_order_qty: f64,
-    #[serde(rename = "qtyStep")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub qty_step: f64,
-    #[serde(rename = "postOnlyMaxOrderQty")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub post_only_max_order_qty: f64,
-    #[serde(rename = "maxMktOrderQty")]
+fn unknown_transfer() -> TransferStatus {
+    TransferStatus::StatusUnknown
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/transfer/inter-transfer-list
+#[derive(Debug, Deserialize, Clone)]
+pub struct TransferRecord {
+    #[serde(rename = "transferId")]
+    pub transfer_id: String,
+    pub coin: String,
     #[serde(deserialize_with = "parse_string_to_f64")]
-    pub max_mkt_order_qty: f64,
-    #[serde(rename = "minNotionalValue")]
+    pub amount: f64,
+    #[serde(rename = "fromAccountType")]
+    pub from_account_type: String,
+    #[serde(rename = "toAccountType")]
+    pub to_account_type: String,
+    // universal transfers only
+    #[serde(rename = "fromMemberId", default)]
+    pub from_member_id: String,
+    #[serde(rename = "toMemberId", default)]
+    pub to_member_id: String,
+    pub timestamp: String,
+    pub status: TransferStatus,
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/deposit/deposit-record
+#[derive(Debug, Deserialize, Clone)]
+pub struct DepositRecord {
+    #[serde(default)]
+    pub id: String,
+    pub coin: String,
+    pub chain: String,
     #[serde(deserialize_with = "parse_string_to_f64")]
-    pub min_notional_value: f64,
+    pub amount: f64,
+    #[serde(rename = "txID")]
+    pub tx_id: String,
+    pub status: DepositStatus,
+    #[serde(rename = "toAddress")]
+    pub to_address: String,
+    pub tag: String,
+    #[serde(rename = "depositFee", default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub deposit_fee: f64,
+    #[serde(rename = "successAt")]
+    pub success_at: String,
+    #[serde(default)]
+    pub confirmations: String,
+    #[serde(rename = "txIndex", default)]
+    pub tx_index: String,
+    #[serde(rename = "blockHash", default)]
+    pub block_hash: String,
 }
 
+// https://bybit-exchange.github.io/docs/v5/asset/deposit/internal-deposit-record
 #[derive(Debug, Deserialize, Clone)]
-pub struct PositionInfo {
-    #[serde(rename = "adlRankIndicator")]
-    pub adl_rank_indicator: i32,
-    #[serde(rename = "autoAddMargin")]
-    pub auto_add_margin: i32,
-    #[serde(rename = "avgPrice")]
+pub struct InternalDepositRecord {
+    pub id: String,
+    pub coin: String,
     #[serde(deserialize_with = "parse_string_to_f64")]
-    pub avg_price: f64,
-    #[serde(rename = "bustPrice")]
-    pub bust_price: String,
+    pub amount: f64,
+    pub status: InternalDepositStatus,
+    // sender email or phone number
+    pub address: String,
     #[serde(rename = "createdTime")]
     pub created_time: String,
-    #[serde(rename = "cumRealisedPnl")]
-    #[serde(deserialize_with = "parse_string_to_option_f64")]
-    pub cum_realised_pnl: Option<f64>,
-    #[serde(rename = "curRealisedPnl")]
+    #[serde(rename = "txID", default)]
+    pub tx_id: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/withdraw/withdraw-record
+#[derive(Debug, Deserialize, Clone)]
+pub struct WithdrawalRecord {
+    #[serde(rename = "withdrawId")]
+    pub withdraw_id: String,
+    #[serde(rename = "txID")]
+    pub tx_id: String,
+    // 0 on-chain, 1 off-chain
+    #[serde(rename = "withdrawType")]
+    pub withdraw_type: i32,
+    pub coin: String,
+    pub chain: String,
     #[serde(deserialize_with = "parse_string_to_f64")]
-    pub cur_realised_pnl: f64,
-    #[serde(rename = "isReduceOnly")]
-    pub is_reduce_only: bool,
-    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub amount: f64,
+    #[serde(rename = "withdrawFee", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub withdraw_fee: f64,
+    pub status: WithdrawStatus,
+    #[serde(rename = "toAddress")]
+    pub to_address: String,
+    pub tag: String,
+    #[serde(rename = "createTime")]
+    pub create_time: String,
+    #[serde(rename = "updateTime")]
+    pub update_time: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/subuid-list
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubMember {
+    pub uid: String,
+    pub username: String,
+    #[serde(rename = "memberType")]
+    pub member_type: SubMemberType,
+    pub status: SubMemberStatus,
+    // 1 classic, 3 uta1.0, 4 uta1.0 pro, 5 uta2.0, 6 uta2.0 pro
+    #[serde(rename = "accountMode", default)]
+    pub account_mode: Option<u8>,
+    #[serde(default)]
+    pub remark: String,
+}
+
+/// Set of API key permissions, decoded from and encoded to bybit's `{"group": ["name"]}` object.
+/// Permissions this crate doesn't know about are kept in `unknown` as `group:name`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiPermissions {
+    pub permissions: BTreeSet<ApiPermission>,
+    pub unknown: Vec<String>,
+}
+
+impl ApiPermissions {
+    pub fn new(permissions: &[ApiPermission]) -> Self {
+        Self {
+            permissions: permissions.iter().copied().collect(),
+            unknown: vec![],
+        }
+    }
+
+    pub fn contains(&self, permission: ApiPermission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    pub fn can_trade(&self) -> bool {
+        self.permissions.iter().any(ApiPermission::is_trading)
+    }
+
+    pub fn can_withdraw(&self) -> bool {
+        self.contains(ApiPermission::Withdraw)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for permission in &self.permissions {
+            let (group, name) = permission.as_parts();
+            groups.entry(group).or_default().push(name);
+        }
+        json!(groups)
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiPermissions {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let groups: HashMap<String, Vec<String>> = HashMap::deserialize(deserializer)?;
+
+        let mut permissions = ApiPermissions::default();
+        for (group, names) in groups {
+            for name in names {
+                match ApiPermission::from_parts(&group, &name) {
+                    Some(permission) => { permissions.permissions.insert(permission); },
+                    None => permissions.unknown.push(format!("{group}:{name}")),
+                }
+            }
+        }
+        permissions.unknown.sort();
+
+        Ok(permissions)
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/create-subuid-apikey
+#[derive(Debug, Clone)]
+pub struct ApiKeySettings {
+    pub note: String,
+    pub read_only: bool,
+    // empty for no IP binding (bybit expires unbound keys after 90 days)
+    pub ips: Vec<String>,
+    pub permissions: ApiPermissions,
+}
+
+impl ApiKeySettings {
+    pub fn new(note: &str) -> Self {
+        Self {
+            note: note.to_string(),
+            read_only: false,
+            ips: vec![],
+            permissions: ApiPermissions::default(),
+        }
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn ip(mut self, ip: &str) -> Self {
+        self.ips.push(ip.to_string());
+        self
+    }
+
+    pub fn permission(mut self, permission: ApiPermission) -> Self {
+        self.permissions.permissions.insert(permission);
+        self
+    }
+
+    pub fn permissions(mut self, permissions: ApiPermissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(self.note.chars().count() <= 20, "note must be at most 20 characters");
+        ensure!(self.ips.len() <= 1000, "at most 1000 ips can be bound");
+        ensure!(!self.ips.iter().any(|ip| ip.is_empty() || ip.contains(',')), "invalid ip in {:?}", self.ips);
+        ensure!(!self.permissions.permissions.is_empty(), "no permissions set");
+        ensure!(!(self.read_only && self.permissions.can_withdraw()), "a read-only key cannot withdraw");
+        Ok(())
+    }
+
+    pub fn into_json(&self) -> anyhow::Result<Value> {
+        self.validate()?;
+
+        let ips = if self.ips.is_empty() { "*".to_string() } else { self.ips.join(",") };
+
+        Ok(json!({
+            "note": self.note,
+            "readOnly": self.read_only as i32,
+            "ips": ips,
+            "permissions": self.permissions.to_json(),
+        }))
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/create-subuid-apikey
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubApiKey {
+    pub id: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    #[serde(rename = "readOnly", deserialize_with = "parse_string_to_bool")]
+    pub read_only: bool,
+    // empty except on creation
+    #[serde(default)]
+    pub secret: String,
+    pub permissions: ApiPermissions,
+    #[serde(default)]
+    pub ips: Vec<String>,
+}
+
+// https://bybit-exchange.github.io/docs/v5/user/apikey-info
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    #[serde(rename = "readOnly", deserialize_with = "parse_string_to_bool")]
+    pub read_only: bool,
+    pub permissions: ApiPermissions,
+    // ["*"] when not bound
+    #[serde(default)]
+    pub ips: Vec<String>,
+    // days left, only meaningful when expired_at is set
+    #[serde(rename = "deadlineDay", default)]
+    pub deadline_day: i64,
+    // empty for keys that don't expire
+    #[serde(rename = "expiredAt", default)]
+    pub expired_at: String,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: String,
+    #[serde(rename = "userID", default)]
+    pub user_id: u64,
+    #[serde(rename = "isMaster", default)]
+    pub is_master: bool,
+    #[serde(rename = "parentUid", default)]
+    pub parent_uid: String,
+}
+
+impl ApiKeyInfo {
+    pub fn is_ip_bound(&self) -> bool {
+        self.ips.iter().any(|ip| ip != "*")
+    }
+
+    pub fn days_to_expiry(&self) -> Option<i64> {
+        if self.expired_at.is_empty() {
+            None
+        } else {
+            Some(self.deadline_day)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreflightPolicy {
+    pub require_trading: bool,
+    pub forbid_withdraw: bool,
+    pub require_ip_binding: bool,
+    pub min_days_to_expiry: i64,
+    pub max_clock_offset_ms: u64,
+}
+
+impl Default for PreflightPolicy {
+    fn default() -> Self {
+        Self {
+            require_trading: true,
+            forbid_withdraw: false,
+            require_ip_binding: false,
+            min_days_to_expiry: 7,
+            // well inside RECV_WINDOW
+            max_clock_offset_ms: 1000,
+        }
+    }
+}
+
+impl PreflightPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn require_trading(mut self, require_trading: bool) -> Self {
+        self.require_trading = require_trading;
+        self
+    }
+
+    pub fn forbid_withdraw(mut self, forbid_withdraw: bool) -> Self {
+        self.forbid_withdraw = forbid_withdraw;
+        self
+    }
+
+    pub fn require_ip_binding(mut self, require_ip_binding: bool) -> Self {
+        self.require_ip_binding = require_ip_binding;
+        self
+    }
+
+    pub fn min_days_to_expiry(mut self, days: i64) -> Self {
+        self.min_days_to_expiry = days;
+        self
+    }
+
+    pub fn max_clock_offset_ms(mut self, max_offset_ms: u64) -> Self {
+        self.max_clock_offset_ms = max_offset_ms;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreflightReport {
+    pub key: ApiKeyInfo,
+    // server minus local
+    pub clock_offset_ms: i64,
+    pub problems: Vec<String>,
+}
+
+impl PreflightReport {
+    pub fn new(key: ApiKeyInfo, clock_offset_ms: i64, policy: &PreflightPolicy) -> Self {
+        let mut problems = vec![];
+
+        if policy.require_trading {
+            if key.read_only {
+                problems.push("api key is read-only".to_string());
+            } else if !key.permissions.can_trade() {
+                problems.push("api key has no trading permission".to_string());
+            }
+        }
+
+        if policy.forbid_withdraw && key.permissions.can_withdraw() {
+            problems.push("api key can withdraw".to_string());
+        }
+
+        if policy.require_ip_binding && !key.is_ip_bound() {
+            problems.push("api key is not bound to any ip".to_string());
+        }
+
+        if let Some(days) = key.days_to_expiry() {
+            if days < policy.min_days_to_expiry {
+                problems.push(format!("api key expires in {days} days (at {})", key.expired_at));
+            }
+        }
+
+        if clock_offset_ms.unsigned_abs() > policy.max_clock_offset_ms {
+            problems.push(format!("clock is off by {clock_offset_ms}ms"));
+        }
+
+        Self {
+            key,
+            clock_offset_ms,
+            problems,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn ensure_ready(&self) -> anyhow::Result<()> {
+        ensure!(self.is_ready(), "preflight failed: {}", self.problems.join("; "));
+        Ok(())
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/convert/convert-coin-list
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConvertCoin {
+    pub coin: String,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    #[serde(rename = "accuracyLength", deserialize_with = "parse_string_to_u64")]
+    pub accuracy_length: u64,
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub balance: f64,
+    // balance in USDT
+    #[serde(rename = "uBalance", deserialize_with = "parse_string_to_f64")]
+    pub u_balance: f64,
+    #[serde(rename = "singleFromMinLimit", deserialize_with = "parse_string_to_f64")]
+    pub single_from_min_limit: f64,
+    #[serde(rename = "singleFromMaxLimit", deserialize_with = "parse_string_to_f64")]
+    pub single_from_max_limit: f64,
+    #[serde(rename = "disableFrom")]
+    pub disable_from: bool,
+    #[serde(rename = "disableTo")]
+    pub disable_to: bool,
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/convert/apply-quote
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConvertQuote {
+    #[serde(rename = "quoteTxId")]
+    pub quote_tx_id: String,
+    #[serde(rename = "exchangeRate", deserialize_with = "parse_string_to_f64")]
+    pub exchange_rate: f64,
+    #[serde(rename = "fromCoin")]
+    pub from_coin: String,
+    #[serde(rename = "toCoin")]
+    pub to_coin: String,
+    #[serde(rename = "fromAmount", deserialize_with = "parse_string_to_f64")]
+    pub from_amount: f64,
+    #[serde(rename = "toAmount", deserialize_with = "parse_string_to_f64")]
+    pub to_amount: f64,
+    #[serde(rename = "expiredTime", deserialize_with = "parse_string_to_u64")]
+    pub expired_time: u64,
+    // not in the response, filled in from the request
+    #[serde(skip, default = "funding_convert_account")]
+    pub account_type: ConvertAccountType,
+}
+
+fn funding_convert_account() -> ConvertAccountType {
+    ConvertAccountType::Funding
+}
+
+impl ConvertQuote {
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.expired_time
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/convert/get-convert-result
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConvertRecord {
+    #[serde(rename = "accountType")]
+    pub account_type: ConvertAccountType,
+    #[serde(rename = "exchangeTxId")]
+    pub exchange_tx_id: String,
+    #[serde(rename = "fromCoin")]
+    pub from_coin: String,
+    #[serde(rename = "toCoin")]
+    pub to_coin: String,
+    #[serde(rename = "fromAmount", deserialize_with = "parse_string_to_f64")]
+    pub from_amount: f64,
+    #[serde(rename = "toAmount", deserialize_with = "parse_string_to_f64")]
+    pub to_amount: f64,
+    #[serde(rename = "exchangeStatus")]
+    pub status: ConvertStatus,
+    #[serde(rename = "convertRate", deserialize_with = "parse_string_to_f64")]
+    pub convert_rate: f64,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/coin-info
+#[derive(Debug, Deserialize, Clone)]
+pub struct CoinMetadata {
+    pub name: String,
+    pub coin: String,
+    // max amount withdrawable per transaction
+    #[serde(rename = "remainAmount", deserialize_with = "parse_string_to_f64")]
+    pub remain_amount: f64,
+    pub chains: Vec<ChainInfo>,
+}
+
+impl CoinMetadata {
+    pub fn chain(&self, chain: &str) -> Option<&ChainInfo> {
+        self.chains.iter().find(|c| c.chain == chain)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChainInfo {
+    pub chain: String,
+    #[serde(rename = "chainType")]
+    pub chain_type: String,
+    // confirmations before the deposit is credited
+    #[serde(deserialize_with = "parse_string_to_u64")]
+    pub confirmation: u64,
+    // confirmations before the deposit is unlocked for withdrawal
+    #[serde(rename = "safeConfirmNumber", default, deserialize_with = "parse_string_to_u64_or_zero")]
+    pub safe_confirm_number: u64,
+    #[serde(rename = "withdrawFee", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub withdraw_fee: f64,
+    #[serde(rename = "withdrawPercentageFee", default, deserialize_with = "parse_string_to_f64_or_zero")]
+    pub withdraw_percentage_fee: f64,
+    #[serde(rename = "depositMin", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub deposit_min: f64,
+    #[serde(rename = "withdrawMin", deserialize_with = "parse_string_to_f64_or_zero")]
+    pub withdraw_min: f64,
+    // decimal places accepted for withdrawal amounts
+    #[serde(rename = "minAccuracy", deserialize_with = "parse_string_to_u64")]
+    pub withdraw_precision: u64,
+    #[serde(rename = "chainDeposit", deserialize_with = "parse_string_to_bool")]
+    pub deposit_enabled: bool,
+    #[serde(rename = "chainWithdraw", deserialize_with = "parse_string_to_bool")]
+    pub withdraw_enabled: bool,
+    #[serde(rename = "contractAddress", default)]
+    pub contract_address: String,
+}
+
+impl ChainInfo {
+    /// Fee bybit will charge for withdrawing `amount`.
+    pub fn withdraw_cost(&self, amount: f64) -> f64 {
+        self.withdraw_fee + amount * self.withdraw_percentage_fee
+    }
+
+    pub fn check_deposit(&self) -> anyhow::Result<()> {
+        ensure!(self.deposit_enabled, "deposits on {} are suspended", self.chain);
+        Ok(())
+    }
+
+    /// Checks withdrawals are open and `amount` meets the minimum and precision of the chain.
+    pub fn check_withdraw(&self, amount: f64) -> anyhow::Result<()> {
+        ensure!(self.withdraw_enabled, "withdrawals on {} are suspended", self.chain);
+        ensure!(amount >= self.withdraw_min, "amount {amount} is below the {} withdraw minimum of {}", self.chain, self.withdraw_min);
+
+        let scale = 10f64.powi(self.withdraw_precision as i32);
+        ensure!(((amount * scale).round() - amount * scale).abs() < 1e-6, "amount {amount} has more than {} decimals", self.withdraw_precision);
+        Ok(())
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/deposit/master-deposit-addr
+#[derive(Debug, Deserialize, Clone)]
+pub struct DepositAddresses {
+    pub coin: String,
+    pub chains: Vec<DepositAddress>,
+}
+
+impl DepositAddresses {
+    pub fn chain(&self, chain: &str) -> Option<&DepositAddress> {
+        self.chains.iter().find(|c| c.chain == chain)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DepositAddress {
+    pub chain: String,
+    #[serde(rename = "chainType")]
+    pub chain_type: String,
+    #[serde(rename = "addressDeposit")]
+    pub address: String,
+    // memo, required for some chains
+    #[serde(rename = "tagDeposit")]
+    pub tag: String,
+    #[serde(rename = "contractAddress", default)]
+    pub contract_address: String,
+}
+
+// https://bybit-exchange.github.io/docs/v5/asset/withdraw
+#[derive(Debug, Clone)]
+pub struct WithdrawRequest {
+    pub coin: String,
+    pub chain: String,
+    pub address: String,
+    pub amount: f64,
+    pub tag: Option<String>,
+    // always sent, bybit's own default depends on the account
+    pub account_type: WithdrawAccountType,
+    pub force_chain: ForceChain,
+    // deduct the fee from amount instead of on top of it
+    pub fee_included: bool,
+    pub request_id: Option<String>,
+}
+
+impl WithdrawRequest {
+    pub fn new(coin: &str, chain: &str, address: &str, amount: f64) -> Self {
+        Self {
+            coin: coin.to_string(),
+            chain: chain.to_string(),
+            address: address.to_string(),
+            amount,
+            tag: None,
+            account_type: WithdrawAccountType::Fund,
+            force_chain: ForceChain::OnChain,
+            fee_included: false,
+            request_id: None,
+        }
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    pub fn account_type(mut self, account_type: WithdrawAccountType) -> Self {
+        self.account_type = account_type;
+        self
+    }
+
+    pub fn force_chain(mut self, force_chain: ForceChain) -> Self {
+        self.force_chain = force_chain;
+        self
+    }
+
+    pub fn fee_included(mut self, fee_included: bool) -> Self {
+        self.fee_included = fee_included;
+        self
+    }
+
+    pub fn request_id(mut self, request_id: &str) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(!self.coin.is_empty(), "coin is empty");
+        ensure!(!self.chain.is_empty(), "chain is empty");
+        ensure!(!self.address.is_empty(), "address is empty");
+        ensure!(self.amount > 0.0 && self.amount.is_finite(), "amount must be positive");
+        Ok(())
+    }
+
+    pub fn into_json(&self, timestamp: u128) -> anyhow::Result<Value> {
+        self.validate()?;
+
+        let mut json = json!({
+            "coin": self.coin,
+            "chain": self.chain,
+            "address": self.address,
+            "amount": self.amount.to_string(),
+            "timestamp": timestamp as u64,
+            "accountType": self.account_type,
+            "forceChain": self.force_chain as i32,
+        });
+
+        if let Some(ref tag) = self.tag {
+            json["tag"] = json!(tag);
+        }
+
+        if self.fee_included {
+            json["feeType"] = json!(1);
+        }
+
+        if let Some(ref request_id) = self.request_id {
+            json["requestId"] = json!(request_id);
+        }
+
+        Ok(json)
+    }
+}
+
+pub type WithdrawalConfirmation = Arc<dyn Fn(&WithdrawRequest) -> bool + Send + Sync>;
+
+/// Client-side guard applied to every withdrawal before it is signed. Fails closed: the
+/// destination must be allowlisted and the coin must have a daily limit (use `f64::INFINITY`
+/// to opt out of capping a coin). Limits reset at 00:00 UTC.
+#[derive(Default, Clone)]
+pub struct WithdrawalPolicy {
+    // (coin, chain, address)
+    allowlist: HashSet<(String, String, String)>,
+    daily_limits: HashMap<String, f64>,
+    confirmation: Option<WithdrawalConfirmation>,
+    // coin -> (utc day, amount withdrawn that day)
+    used: HashMap<String, (u64, f64)>,
+}
+
+impl WithdrawalPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, coin: &str, chain: &str, address: &str) -> Self {
+        self.allowlist.insert((coin.to_string(), chain.to_string(), address.to_string()));
+        self
+    }
+
+    pub fn daily_limit(mut self, coin: &str, limit: f64) -> Self {
+        self.daily_limits.insert(coin.to_string(), limit);
+        self
+    }
+
+    /// Called with every withdrawal that passed the allowlist and limits; returning false aborts it.
+    pub fn confirm_with<F: Fn(&WithdrawRequest) -> bool + Send + Sync + 'static>(mut self, confirmation: F) -> Self {
+        self.confirmation = Some(Arc::new(confirmation));
+        self
+    }
+
+    pub fn confirmation(&self) -> Option<WithdrawalConfirmation> {
+        self.confirmation.clone()
+    }
+
+    pub fn is_allowed(&self, coin: &str, chain: &str, address: &str) -> bool {
+        self.allowlist.contains(&(coin.to_string(), chain.to_string(), address.to_string()))
+    }
+
+    pub fn used_today(&self, coin: &str, now_ms: u64) -> f64 {
+        match self.used.get(coin) {
+            Some((day, amount)) if *day == now_ms / DAY_MS => *amount,
+            _ => 0.0,
+        }
+    }
+
+    pub fn remaining_today(&self, coin: &str, now_ms: u64) -> f64 {
+        match self.daily_limits.get(coin) {
+            Some(limit) => (limit - self.used_today(coin, now_ms)).max(0.0),
+            None => 0.0,
+        }
+    }
+
+    pub fn check(&self, request: &WithdrawRequest, now_ms: u64) -> anyhow::Result<()> {
+        request.validate()?;
+
+        ensure!(
+            self.is_allowed(&request.coin, &request.chain, &request.address),
+            "withdrawal of {} to {} on {} is not allowlisted", request.coin, request.address, request.chain
+        );
+
+        let limit = self.daily_limits.get(&request.coin)
+            .ok_or_else(|| anyhow::anyhow!("no daily withdrawal limit set for {}", request.coin))?;
+        let used = self.used_today(&request.coin, now_ms);
+
+        ensure!(
+            used + request.amount <= *limit,
+            "withdrawal of {} {} exceeds the daily limit of {} ({} already used)", request.amount, request.coin, limit, used
+        );
+
+        Ok(())
+    }
+
+    /// Checks `request` and counts it against today's limit.
+    pub fn reserve(&mut self, request: &WithdrawRequest, now_ms: u64) -> anyhow::Result<()> {
+        self.check(request, now_ms)?;
+
+        let used = self.used_today(&request.coin, now_ms);
+        self.used.insert(request.coin.clone(), (now_ms / DAY_MS, used + request.amount));
+        Ok(())
+    }
+
+    /// Takes over the daily usage recorded by `previous`, so swapping policies can't reset the limits.
+    pub fn carry_usage_from(&mut self, previous: &WithdrawalPolicy) {
+        for (coin, (day, amount)) in &previous.used {
+            let entry = self.used.entry(coin.clone()).or_insert((*day, 0.0));
+            if entry.0 == *day {
+                entry.1 = entry.1.max(*amount);
+            } else if entry.0 < *day {
+                *entry = (*day, *amount);
+            }
+        }
+    }
+
+    /// Gives back a reservation for a withdrawal bybit rejected.
+    pub fn release(&mut self, request: &WithdrawRequest, now_ms: u64) {
+        if let Some((day, amount)) = self.used.get_mut(&request.coin) {
+            if *day == now_ms / DAY_MS {
+                *amount = (*amount - request.amount).max(0.0);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WithdrawalPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithdrawalPolicy")
+            .field("allowlist", &self.allowlist)
+            .field("daily_limits", &self.daily_limits)
+            .field("confirmation", &self.confirmation.is_some())
+            .field("used", &self.used)
+            .finish()
+    }
+}
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+// https://bybit-exchange.github.io/docs/v5/spot-margin-uta/status
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpotMarginState {
+    #[serde(rename = "spotLeverage", deserialize_with = "parse_string_to_f64")]
+    pub spot_leverage: f64,
+    #[serde(rename = "spotMarginMode", deserialize_with = "parse_string_to_bool")]
+    pub spot_margin_mode: bool,
+    #[serde(rename = "effectiveLeverage", default, deserialize_with = "parse_string_to_f64")]
+    pub effective_leverage: f64,
+}
+
+// https://bybit-exchange.github.io/docs/v5/spot-margin-uta/vip-margin
+#[derive(Debug, Deserialize, Clone)]
+pub struct VipMarginData {
+    #[serde(rename = "vipLevel")]
+    pub vip_level: String,
+    pub list: Vec<VipMarginCoin>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct VipMarginCoin {
+    pub currency: String,
+    pub borrowable: bool,
+    #[serde(rename = "collateralRatio", deserialize_with = "parse_string_to_f64")]
+    pub collateral_ratio: f64,
+    #[serde(rename = "hourlyBorrowRate", deserialize_with = "parse_string_to_f64")]
+    pub hourly_borrow_rate: f64,
+    #[serde(rename = "liquidationOrder")]
+    pub liquidation_order: i32,
+    #[serde(rename = "marginCollateral")]
+    pub margin_collateral: bool,
+    #[serde(rename = "maxBorrowingAmount", deserialize_with = "parse_string_to_f64")]
+    pub max_borrowing_amount: f64,
+}
+
+// https://bybit-exchange.github.io/docs/v5/spot-margin-uta/tier-collateral-ratio
+#[derive(Debug, Deserialize, Clone)]
+pub struct CollateralRatioInfo {
+    pub currency: String,
+    #[serde(rename = "collateralRatioList")]
+    pub collateral_ratio_list: Vec<CollateralRatioTier>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CollateralRatioTier {
+    #[serde(rename = "minQty", deserialize_with = "parse_string_to_f64")]
+    pub min_qty: f64,
+    #[serde(rename = "maxQty", deserialize_with = "parse_string_to_option_f64")]
+    pub max_qty: Option<f64>,
+    #[serde(rename = "collateralRatio", deserialize_with = "parse_string_to_f64")]
+    pub collateral_ratio: f64,
+}
+
+// https://bybit-exchange.github.io/docs/v5/spot-margin-uta/interest-rate-history
+#[derive(Debug, Deserialize, Clone)]
+pub struct InterestRateRecord {
+    pub timestamp: u64,
+    pub currency: String,
+    #[serde(rename = "hourlyBorrowRate", deserialize_with = "parse_string_to_f64")]
+    pub hourly_borrow_rate: f64,
+    #[serde(rename = "vipLevel")]
+    pub vip_level: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContractInfo {
+    pub symbol: String,
+    #[serde(rename = "contractType")]
+    pub contract_type: String,
+    pub status: String,
+    #[serde(rename = "baseCoin")]
+    pub base_coin: String,
+    #[serde(rename = "quoteCoin")]
+    pub quote_coin: String,
+    #[serde(rename = "launchTime")]
+    pub launch_time: String,
+    #[serde(rename = "deliveryTime")]
+    pub delivery_time: String,
+    #[serde(rename = "deliveryFeeRate")]
+    pub delivery_fee_rate: String,
+    #[serde(rename = "priceScale")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub price_scale: f64,
+    #[serde(rename = "leverageFilter")]
+    pub leverage_filter: LeverageFilter,
+    #[serde(rename = "priceFilter")]
+    pub price_filter: PriceFilter,
+    #[serde(rename = "lotSizeFilter")]
+    pub lot_size_filter: LotSizeFilter,
+    #[serde(rename = "unifiedMarginTrade")]
+    pub unified_margin_trade: bool,
+    #[serde(rename = "fundingInterval")]
+    pub funding_interval: i32,
+    #[serde(rename = "settleCoin")]
+    pub settle_coin: String,
+    #[serde(rename = "copyTrading")]
+    pub copy_trading: String,
+    #[serde(rename = "upperFundingRate")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub upper_funding_rate: f64,
+    #[serde(rename = "lowerFundingRate")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub lower_funding_rate: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeverageFilter {
+    #[serde(rename = "minLeverage")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub min_leverage: f64,
+    #[serde(rename = "maxLeverage")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub max_leverage: f64,
+    #[serde(rename = "leverageStep")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub leverage_step: f64,
+}
+
+impl LeverageFilter {
+    /// Checks `leverage` is within bounds and on a `leverage_step` increment.
+    pub fn check(&self, leverage: f64) -> anyhow::Result<()> {
+        ensure!(
+            leverage >= self.min_leverage && leverage <= self.max_leverage,
+            "leverage {leverage} outside [{}, {}]", self.min_leverage, self.max_leverage
+        );
+
+        if self.leverage_step > 0.0 {
+            let steps = (leverage - self.min_leverage) / self.leverage_step;
+            ensure!((steps - steps.round()).abs() < 1e-6, "leverage {leverage} is not a multiple of step {}", self.leverage_step);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceFilter {
+    #[serde(rename = "minPrice")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub min_price: f64,
+    #[serde(rename = "maxPrice")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub max_price: f64,
+    #[serde(rename = "tickSize")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub tick_size: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LotSizeFilter {
+    #[serde(rename = "maxOrderQty")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub max_order_qty: f64,
+    #[serde(rename = "minOrderQty")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub min_order_qty: f64,
+    #[serde(rename = "qtyStep")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub qty_step: f64,
+    #[serde(rename = "postOnlyMaxOrderQty")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub post_only_max_order_qty: f64,
+    #[serde(rename = "maxMktOrderQty")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub max_mkt_order_qty: f64,
+    #[serde(rename = "minNotionalValue")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub min_notional_value: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PositionInfo {
+    #[serde(rename = "adlRankIndicator")]
+    pub adl_rank_indicator: i32,
+    #[serde(rename = "autoAddMargin")]
+    pub auto_add_margin: i32,
+    #[serde(rename = "avgPrice")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub avg_price: f64,
+    #[serde(rename = "bustPrice")]
+    pub bust_price: String,
+    #[serde(rename = "createdTime")]
+    pub created_time: String,
+    #[serde(rename = "cumRealisedPnl")]
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub cum_realised_pnl: Option<f64>,
+    #[serde(rename = "curRealisedPnl")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub cur_realised_pnl: f64,
+    #[serde(rename = "isReduceOnly")]
+    pub is_reduce_only: bool,
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
     pub leverage: Option<f64>,
     #[serde(rename = "leverageSysUpdatedTime")]
     pub leverage_sys_updated_time: String,
@@ -321,14 +1667,15 @@ pub struct PositionInfo {
     #[serde(rename = "positionBalance")]
     #[serde(deserialize_with = "parse_string_to_option_f64")]
     pub position_balance: Option<f64>,
+    // empty for option positions and in portfolio margin mode
     #[serde(rename = "positionIM")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub position_im: f64,
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub position_im: Option<f64>,
     #[serde(rename = "positionIdx")]
-    pub position_idx: i32,
+    pub position_idx: PositionIdx,
     #[serde(rename = "positionMM")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub position_mm: f64,
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub position_mm: Option<f64>,
     #[serde(rename = "positionStatus")]
     pub position_status: String,
     #[serde(rename = "positionValue")]
@@ -342,205 +1689,832 @@ pub struct PositionInfo {
     pub seq: i64,
     #[serde(rename = "sessionAvgPrice")]
     pub session_avg_price: String,
-    pub side: String,
+    pub side: PositionSide,
     #[serde(deserialize_with = "parse_string_to_f64")]
     pub size: f64,
     #[serde(rename = "stopLoss")]
-    pub stop_loss: String,
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub stop_loss: Option<f64>,
+    pub symbol: String,
+    #[serde(rename = "takeProfit")]
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub take_profit: Option<f64>,
+    #[serde(rename = "tpslMode")]
+    #[serde(deserialize_with = "parse_empty_string_to_none")]
+    pub tpsl_mode: Option<TpslMode>,
+    #[serde(rename = "tradeMode")]
+    pub trade_mode: i32,
+    #[serde(rename = "trailingStop")]
+    #[serde(deserialize_with = "parse_string_to_option_f64")]
+    pub trailing_stop: Option<f64>,
+    #[serde(rename = "unrealisedPnl")]
+    #[serde(deserialize_with = "parse_string_to_f64")]
+    pub unrealised_pnl: f64,
+    #[serde(rename = "updatedTime")]
+    pub updated_time: String,
+}
+
+impl PositionInfo {
+    /// Size signed by side: positive long, negative short, zero when flat.
+    pub fn signed_size(&self) -> f64 {
+        match self.side {
+            PositionSide::Buy => self.size,
+            PositionSide::Sell => -self.size,
+            PositionSide::None => 0.0,
+        }
+    }
+}
+
+/// Net signed size per symbol, summing the long and short legs of hedge-mode positions.
+pub fn net_positions<'a>(positions: impl IntoIterator<Item = &'a PositionInfo>) -> HashMap<String, f64> {
+    let mut net: HashMap<String, f64> = HashMap::default();
+
+    for position in positions {
+        *net.entry(position.symbol.clone()).or_default() += position.signed_size();
+    }
+
+    net
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TickerData {
+    #[serde(rename = "ask1Price")]
+    pub ask1_price: String,
+    #[serde(rename = "ask1Size")]
+    pub ask1_size: String,
+    pub basis: String,
+    #[serde(rename = "basisRate")]
+    pub basis_rate: String,
+    #[serde(rename = "bid1Price")]
+    pub bid1_price: String,
+    #[serde(rename = "bid1Size")]
+    pub bid1_size: String,
+    #[serde(rename = "curPreListingPhase")]
+    pub cur_pre_listing_phase: String,
+    #[serde(rename = "deliveryFeeRate")]
+    pub delivery_fee_rate: String,
+    #[serde(rename = "deliveryTime")]
+    pub delivery_time: String,
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: String,
+    #[serde(rename = "highPrice24h")]
+    pub high_price_24h: String,
+    #[serde(rename = "indexPrice")]
+    pub index_price: String,
+    #[serde(rename = "lastPrice")]
+    pub last_price: String,
+    #[serde(rename = "lowPrice24h")]
+    pub low_price_24h: String,
+    #[serde(rename = "markPrice")]
+    pub mark_price: String,
+    #[serde(rename = "nextFundingTime")]
+    pub next_funding_time: String,
+    #[serde(rename = "openInterest")]
+    pub open_interest: String,
+    #[serde(rename = "openInterestValue")]
+    pub open_interest_value: String,
+    #[serde(rename = "prevPrice1h")]
+    pub prev_price_1h: String,
+    #[serde(rename = "prevPrice24h")]
+    pub prev_price_24h: String,
+    #[serde(rename = "price24hPcnt")]
+    pub price_24h_pcnt: String,
+    pub symbol: String,
+    #[serde(rename = "turnover24h")]
+    pub turnover_24h: String,
+    #[serde(rename = "volume24h")]
+    pub volume_24h: String,
+}
+
+impl TickerData {
+    pub fn annualized_funding(&self, funding_interval: u64) -> anyhow::Result<f64> {
+        let frequenzy = funding_interval as f64 / 60.0 / 24.0; // 4
+        let r = self.funding_rate.parse::<f64>()? * 365.0 / frequenzy;
+        Ok(r)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpotTickerData {
+    #[serde(rename = "ask1Price", deserialize_with = "parse_string_to_f64")]
+    pub ask1_price: f64,
+    #[serde(rename = "ask1Size", deserialize_with = "parse_string_to_f64")]
+    pub ask1_size: f64,
+    #[serde(rename = "bid1Price", deserialize_with = "parse_string_to_f64")]
+    pub bid1_price: f64,
+    #[serde(rename = "bid1Size", deserialize_with = "parse_string_to_f64")]
+    pub bid1_size: f64,
+    #[serde(rename = "highPrice24h", deserialize_with = "parse_string_to_f64")]
+    pub high_price_24h: f64,
+    #[serde(rename = "lastPrice", deserialize_with = "parse_string_to_f64")]
+    pub last_price: f64,
+    #[serde(rename = "lowPrice24h", deserialize_with = "parse_string_to_f64")]
+    pub low_price_24h: f64,
+    pub symbol: String,
+    #[serde(rename = "turnover24h", deserialize_with = "parse_string_to_f64")]
+    pub turnover_24h: f64,
+    #[serde(rename = "volume24h", deserialize_with = "parse_string_to_f64")]
+    pub volume_24h: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OptionTickerData {
+    // ───── level-1 quotes ─────
+    #[serde(rename = "ask1Iv",   deserialize_with = "parse_string_to_f64")]
+    pub ask1_iv: f64,
+    #[serde(rename = "ask1Price", deserialize_with = "parse_string_to_f64")]
+    pub ask1_price: f64,
+    #[serde(rename = "ask1Size",  deserialize_with = "parse_string_to_f64")]
+    pub ask1_size: f64,
+
+    #[serde(rename = "bid1Iv",    deserialize_with = "parse_string_to_f64")]
+    pub bid1_iv: f64,
+    #[serde(rename = "bid1Price", deserialize_with = "parse_string_to_f64")]
+    pub bid1_price: f64,
+    #[serde(rename = "bid1Size",  deserialize_with = "parse_string_to_f64")]
+    pub bid1_size: f64,
+
+
+    // ───── basic greeks & mark ─────
+    #[serde(rename = "delta",     deserialize_with = "parse_string_to_f64")]
+    pub delta: f64,
+    #[serde(rename = "gamma",     deserialize_with = "parse_string_to_f64")]
+    pub gamma: f64,
+    #[serde(rename = "theta",     deserialize_with = "parse_string_to_f64")]
+    pub theta: f64,
+    #[serde(rename = "vega",      deserialize_with = "parse_string_to_f64")]
+    pub vega: f64,
+    #[serde(rename = "markIv",    deserialize_with = "parse_string_to_f64")]
+    pub mark_iv: f64,
+    #[serde(rename = "markPrice", deserialize_with = "parse_string_to_f64")]
+    pub mark_price: f64,
+
+    // ───── spot / index linkage ─────
+    #[serde(rename = "indexPrice",       deserialize_with = "parse_string_to_f64")]
+    pub index_price: f64,
+    #[serde(rename = "underlyingPrice",  deserialize_with = "parse_string_to_f64")]
+    pub underlying_price: f64,
+    #[serde(rename = "predictedDeliveryPrice", deserialize_with = "parse_string_to_f64")]
+    pub predicted_delivery_price: f64,
+
+
+    // ───── 24-h stats ─────
+    #[serde(rename = "change24h",     deserialize_with = "parse_string_to_f64")]
+    pub change_24h: f64,
+    #[serde(rename = "highPrice24h",  deserialize_with = "parse_string_to_f64")]
+    pub high_price_24h: f64,
+    #[serde(rename = "lowPrice24h",   deserialize_with = "parse_string_to_f64")]
+    pub low_price_24h: f64,
+    #[serde(rename = "turnover24h",   deserialize_with = "parse_string_to_f64")]
+    pub turnover_24h: f64,
+    #[serde(rename = "volume24h",     deserialize_with = "parse_string_to_f64")]
+    pub volume_24h: f64,
+
+    // ───── running totals ─────
+    #[serde(rename = "totalTurnover", deserialize_with = "parse_string_to_f64")]
+    pub total_turnover: f64,
+    #[serde(rename = "totalVolume",   deserialize_with = "parse_string_to_f64")]
+    pub total_volume: f64,
+
+    // ───── position-level ─────
+    #[serde(rename = "openInterest", deserialize_with = "parse_string_to_f64")]
+    pub open_interest: f64,
+
+    // ───── anything that is already a string ─────
     pub symbol: String,
-    #[serde(rename = "takeProfit")]
-    pub take_profit: String,
-    #[serde(rename = "tpslMode")]
-    pub tpsl_mode: String,
-    #[serde(rename = "tradeMode")]
-    pub trade_mode: i32,
-    #[serde(rename = "trailingStop")]
-    #[serde(deserialize_with = "parse_string_to_option_f64")]
-    pub trailing_stop: Option<f64>,
-    #[serde(rename = "unrealisedPnl")]
-    #[serde(deserialize_with = "parse_string_to_f64")]
-    pub unrealised_pnl: f64,
-    #[serde(rename = "updatedTime")]
-    pub updated_time: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct TickerData {
-    #[serde(rename = "ask1Price")]
-    pub ask1_price: String,
-    #[serde(rename = "ask1Size")]
-    pub ask1_size: String,
-    pub basis: String,
-    #[serde(rename = "basisRate")]
-    pub basis_rate: String,
-    #[serde(rename = "bid1Price")]
-    pub bid1_price: String,
-    #[serde(rename = "bid1Size")]
-    pub bid1_size: String,
-    #[serde(rename = "curPreListingPhase")]
-    pub cur_pre_listing_phase: String,
-    #[serde(rename = "deliveryFeeRate")]
-    pub delivery_fee_rate: String,
-    #[serde(rename = "deliveryTime")]
-    pub delivery_time: String,
-    #[serde(rename = "fundingRate")]
-    pub funding_rate: String,
-    #[serde(rename = "highPrice24h")]
-    pub high_price_24h: String,
-    #[serde(rename = "indexPrice")]
-    pub index_price: String,
-    #[serde(rename = "lastPrice")]
-    pub last_price: String,
-    #[serde(rename = "lowPrice24h")]
-    pub low_price_24h: String,
-    #[serde(rename = "markPrice")]
-    pub mark_price: String,
-    #[serde(rename = "nextFundingTime")]
-    pub next_funding_time: String,
-    #[serde(rename = "openInterest")]
-    pub open_interest: String,
-    #[serde(rename = "openInterestValue")]
-    pub open_interest_value: String,
-    #[serde(rename = "prevPrice1h")]
-    pub prev_price_1h: String,
-    #[serde(rename = "prevPrice24h")]
-    pub prev_price_24h: String,
-    #[serde(rename = "price24hPcnt")]
-    pub price_24h_pcnt: String,
+// https://bybit-exchange.github.io/docs/v5/order/create-order
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
     pub symbol: String,
-    #[serde(rename = "turnover24h")]
-    pub turnover_24h: String,
-    #[serde(rename = "volume24h")]
-    pub volume_24h: String,
+    pub side: TradeDirection,
+    pub order_type: OrderType,
+    pub qty: f64,
+    pub price: Option<f64>,
+    pub time_in_force: Option<TimeInForce>,
+    pub order_link_id: Option<String>,
+    pub reduce_only: Option<bool>,
+    pub close_on_trigger: Option<bool>,
+    pub position_idx: Option<PositionIdx>,
+    pub is_leverage: Option<bool>,
+    pub market_unit: Option<MarketUnit>,
+    pub smp_type: Option<SmpType>,
+    pub order_iv: Option<f64>,
+    pub slippage_tolerance: Option<SlippageTolerance>,
+    pub take_profit: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub tpsl_mode: Option<TpslMode>,
+    pub tp_trigger_by: Option<TriggerBy>,
+    pub sl_trigger_by: Option<TriggerBy>,
+    pub tp_limit_price: Option<f64>,
+    pub sl_limit_price: Option<f64>,
+    pub tp_order_type: Option<OrderType>,
+    pub sl_order_type: Option<OrderType>,
+    pub trigger_price: Option<f64>,
+    pub trigger_direction: Option<TriggerDirection>,
+    pub trigger_by: Option<TriggerBy>,
+    pub order_filter: Option<OrderFilter>,
+    pub mmp: Option<bool>,
+}
+
+impl OrderRequest {
+    pub fn new(symbol: &str, side: TradeDirection, order_type: OrderType, qty: f64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side,
+            order_type,
+            qty,
+            price: None,
+            time_in_force: None,
+            order_link_id: None,
+            reduce_only: None,
+            close_on_trigger: None,
+            position_idx: None,
+            is_leverage: None,
+            market_unit: None,
+            smp_type: None,
+            order_iv: None,
+            slippage_tolerance: None,
+            take_profit: None,
+            stop_loss: None,
+            tpsl_mode: None,
+            tp_trigger_by: None,
+            sl_trigger_by: None,
+            tp_limit_price: None,
+            sl_limit_price: None,
+            tp_order_type: None,
+            sl_order_type: None,
+            trigger_price: None,
+            trigger_direction: None,
+            trigger_by: None,
+            order_filter: None,
+            mmp: None,
+        }
+    }
+
+    pub fn market(symbol: &str, side: TradeDirection, qty: f64) -> Self {
+        Self::new(symbol, side, OrderType::Market, qty)
+    }
+
+    pub fn limit(symbol: &str, side: TradeDirection, qty: f64, price: f64) -> Self {
+        Self::new(symbol, side, OrderType::Limit, qty).price(price)
+    }
+
+    /// Market order placed once the price crosses `trigger_price` in `direction`.
+    pub fn stop_market(symbol: &str, side: TradeDirection, qty: f64, trigger_price: f64, direction: TriggerDirection) -> Self {
+        Self::market(symbol, side, qty).trigger(trigger_price, direction)
+    }
+
+    /// Limit order at `price` placed once the price crosses `trigger_price` in `direction`.
+    pub fn stop_limit(symbol: &str, side: TradeDirection, qty: f64, price: f64, trigger_price: f64, direction: TriggerDirection) -> Self {
+        Self::limit(symbol, side, qty, price).trigger(trigger_price, direction)
+    }
+
+    pub fn price(mut self, price: f64) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn order_link_id(mut self, order_link_id: &str) -> Self {
+        self.order_link_id = Some(order_link_id.to_string());
+        self
+    }
+
+    pub fn reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = Some(reduce_only);
+        self
+    }
+
+    pub fn close_on_trigger(mut self, close_on_trigger: bool) -> Self {
+        self.close_on_trigger = Some(close_on_trigger);
+        self
+    }
+
+    pub fn position_idx(mut self, position_idx: PositionIdx) -> Self {
+        self.position_idx = Some(position_idx);
+        self
+    }
+
+    pub fn is_leverage(mut self, is_leverage: bool) -> Self {
+        self.is_leverage = Some(is_leverage);
+        self
+    }
+
+    pub fn market_unit(mut self, market_unit: MarketUnit) -> Self {
+        self.market_unit = Some(market_unit);
+        self
+    }
+
+    pub fn smp_type(mut self, smp_type: SmpType) -> Self {
+        self.smp_type = Some(smp_type);
+        self
+    }
+
+    pub fn order_iv(mut self, order_iv: f64) -> Self {
+        self.order_iv = Some(order_iv);
+        self
+    }
+
+    pub fn slippage_tolerance(mut self, slippage_tolerance: SlippageTolerance) -> Self {
+        self.slippage_tolerance = Some(slippage_tolerance);
+        self
+    }
+
+    pub fn take_profit(mut self, take_profit: f64) -> Self {
+        self.take_profit = Some(take_profit);
+        self
+    }
+
+    pub fn stop_loss(mut self, stop_loss: f64) -> Self {
+        self.stop_loss = Some(stop_loss);
+        self
+    }
+
+    pub fn tpsl_mode(mut self, tpsl_mode: TpslMode) -> Self {
+        self.tpsl_mode = Some(tpsl_mode);
+        self
+    }
+
+    pub fn tp_trigger_by(mut self, trigger_by: TriggerBy) -> Self {
+        self.tp_trigger_by = Some(trigger_by);
+        self
+    }
+
+    pub fn sl_trigger_by(mut self, trigger_by: TriggerBy) -> Self {
+        self.sl_trigger_by = Some(trigger_by);
+        self
+    }
+
+    /// Take profit as a limit order at `limit_price` (partial mode only for derivatives).
+    pub fn tp_limit(mut self, limit_price: f64) -> Self {
+        self.tp_order_type = Some(OrderType::Limit);
+        self.tp_limit_price = Some(limit_price);
+        self
+    }
+
+    /// Stop loss as a limit order at `limit_price` (partial mode only for derivatives).
+    pub fn sl_limit(mut self, limit_price: f64) -> Self {
+        self.sl_order_type = Some(OrderType::Limit);
+        self.sl_limit_price = Some(limit_price);
+        self
+    }
+
+    /// Makes this a conditional order. For spot the direction is implied and the
+    /// order is sent with `orderFilter=StopOrder`.
+    pub fn trigger(mut self, trigger_price: f64, direction: TriggerDirection) -> Self {
+        self.trigger_price = Some(trigger_price);
+        self.trigger_direction = Some(direction);
+        self
+    }
+
+    pub fn trigger_by(mut self, trigger_by: TriggerBy) -> Self {
+        self.trigger_by = Some(trigger_by);
+        self
+    }
+
+    pub fn order_filter(mut self, order_filter: OrderFilter) -> Self {
+        self.order_filter = Some(order_filter);
+        self
+    }
+
+    /// Counts the order towards the base coin's market maker protection limits (options only).
+    pub fn mmp(mut self, mmp: bool) -> Self {
+        self.mmp = Some(mmp);
+        self
+    }
+
+    /// Rejects parameter combinations bybit would refuse (or silently ignore) for `category`.
+    pub fn validate(&self, category: Category) -> anyhow::Result<()> {
+        ensure!(self.qty > 0.0, "qty must be positive");
+
+        if let Some(p) = self.price {
+            ensure!(p > 0.0, "price must be positive");
+        }
+
+        match self.order_type {
+            OrderType::Limit => {
+                ensure!(self.price.is_some() || self.order_iv.is_some(), "limit order missing price");
+                ensure!(self.market_unit.is_none(), "market_unit is only valid for market orders");
+                ensure!(self.slippage_tolerance.is_none(), "slippage_tolerance is only valid for market orders");
+            },
+            OrderType::Market => {
+                ensure!(self.price.is_none(), "market order must not have a price");
+                ensure!(self.order_iv.is_none(), "market order must not have an order_iv");
+                ensure!(
+                    !matches!(self.time_in_force, Some(TimeInForce::PostOnly) | Some(TimeInForce::RPI)),
+                    "market order cannot be PostOnly or RPI"
+                );
+            }
+        }
+
+        if let Some(link_id) = &self.order_link_id {
+            ensure!(!link_id.is_empty() && link_id.len() <= 36, "order_link_id must be 1-36 characters");
+        }
+
+        match self.slippage_tolerance {
+            Some(SlippageTolerance::TickSize(ticks)) => ensure!(ticks > 0.0, "slippage tolerance must be positive"),
+            Some(SlippageTolerance::Percent(pct)) => ensure!(pct > 0.0, "slippage tolerance must be positive"),
+            None => {}
+        }
+
+        if category.eq(&Category::Spot) {
+            ensure!(self.reduce_only.is_none(), "reduce_only is not supported for spot");
+            ensure!(self.close_on_trigger.is_none(), "close_on_trigger is not supported for spot");
+            ensure!(self.position_idx.is_none(), "position_idx is not supported for spot");
+        } else {
+            ensure!(self.is_leverage.is_none(), "is_leverage is only valid for spot");
+            ensure!(self.market_unit.is_none(), "market_unit is only valid for spot");
+        }
+
+        if category.eq(&Category::Option) {
+            ensure!(self.order_link_id.is_some(), "option orders require an order_link_id");
+            ensure!(self.position_idx.is_none(), "position_idx is not supported for options");
+        } else {
+            ensure!(self.order_iv.is_none(), "order_iv is only valid for options");
+            ensure!(self.mmp.is_none(), "mmp is only valid for options");
+        }
+
+        if self.mmp.eq(&Some(true)) {
+            ensure!(self.order_type.eq(&OrderType::Limit), "mmp is only valid for limit orders");
+        }
+
+        self.validate_trigger(category)?;
+        self.validate_tpsl(category)
+    }
+
+    fn validate_trigger(&self, category: Category) -> anyhow::Result<()> {
+        let Some(trigger_price) = self.trigger_price else {
+            ensure!(self.trigger_by.is_none(), "trigger_by given without trigger_price");
+            ensure!(!self.order_filter.eq(&Some(OrderFilter::StopOrder)), "StopOrder filter requires a trigger_price");
+            return Ok(());
+        };
+
+        ensure!(trigger_price > 0.0, "trigger_price must be positive");
+        ensure!(!category.eq(&Category::Option), "conditional orders are not supported for options");
+
+        if category.eq(&Category::Spot) {
+            ensure!(self.trigger_by.is_none(), "trigger_by is not supported for spot");
+            ensure!(
+                matches!(self.order_filter, None | Some(OrderFilter::StopOrder) | Some(OrderFilter::TpslOrder)),
+                "spot conditional orders need the StopOrder or tpslOrder filter"
+            );
+        } else {
+            ensure!(self.trigger_direction.is_some(), "conditional order missing trigger_direction");
+            ensure!(self.order_filter.is_none(), "order_filter is only valid for spot");
+        }
+
+        Ok(())
+    }
+
+    fn validate_tpsl(&self, category: Category) -> anyhow::Result<()> {
+        let has_tpsl = self.take_profit.is_some() || self.stop_loss.is_some();
+
+        if !has_tpsl {
+            ensure!(
+                self.tpsl_mode.is_none() && self.tp_order_type.is_none() && self.sl_order_type.is_none(),
+                "tpsl settings given without take_profit or stop_loss"
+            );
+            return Ok(());
+        }
+
+        ensure!(!category.eq(&Category::Option), "take_profit/stop_loss is not supported for options");
+
+        if category.eq(&Category::Spot) {
+            ensure!(self.order_type.eq(&OrderType::Limit), "spot take_profit/stop_loss requires a limit order");
+            ensure!(self.tpsl_mode.is_none(), "tpsl_mode is not supported for spot");
+        }
+
+        for (name, price) in [("take_profit", self.take_profit), ("stop_loss", self.stop_loss)] {
+            if let Some(p) = price {
+                ensure!(p > 0.0, "{name} must be positive");
+            }
+        }
+
+        ensure!(self.take_profit.is_some() || (self.tp_trigger_by.is_none() && self.tp_order_type.is_none()), "take profit settings given without take_profit");
+        ensure!(self.stop_loss.is_some() || (self.sl_trigger_by.is_none() && self.sl_order_type.is_none()), "stop loss settings given without stop_loss");
+
+        let limit_legs = [self.tp_order_type, self.sl_order_type].contains(&Some(OrderType::Limit));
+
+        if limit_legs && !category.eq(&Category::Spot) {
+            ensure!(self.tpsl_mode.eq(&Some(TpslMode::Partial)), "limit take_profit/stop_loss requires Partial tpsl_mode");
+        }
+
+        ensure!(self.tp_order_type.eq(&Some(OrderType::Limit)) == self.tp_limit_price.is_some(), "tp_limit_price requires a Limit tp order type");
+        ensure!(self.sl_order_type.eq(&Some(OrderType::Limit)) == self.sl_limit_price.is_some(), "sl_limit_price requires a Limit sl order type");
+
+        Ok(())
+    }
+
+    /// Validates and serializes the order, without the `category` field (batch requests carry it once).
+    pub fn into_json(&self, category: Category) -> anyhow::Result<serde_json::Value> {
+
+        self.validate(category)?;
+
+        let mut params = json!({
+            "symbol": self.symbol,
+            "side": self.side,
+            "qty": self.qty.to_string(),
+            "orderType": self.order_type,
+        });
+
+        if let Some(p) = self.price {
+            params["price"] = json!(p.to_string());
+        }
+
+        if let Some(tip) = self.time_in_force {
+            params["timeInForce"] = json!(tip);
+        }
+
+        if let Some(link_id) = &self.order_link_id {
+            params["orderLinkId"] = json!(link_id);
+        }
+
+        if let Some(reduce_only) = self.reduce_only {
+            params["reduceOnly"] = json!(reduce_only);
+        }
+
+        if let Some(close_on_trigger) = self.close_on_trigger {
+            params["closeOnTrigger"] = json!(close_on_trigger);
+        }
+
+        if let Some(idx) = self.position_idx {
+            params["positionIdx"] = json!(idx);
+        }
+
+        if let Some(is_leverage) = self.is_leverage {
+            params["isLeverage"] = json!(is_leverage as i32);
+        }
+
+        if let Some(unit) = self.market_unit {
+            params["marketUnit"] = json!(unit);
+        }
+
+        if let Some(smp) = self.smp_type {
+            params["smpType"] = json!(smp);
+        }
+
+        if let Some(iv) = self.order_iv {
+            params["orderIv"] = json!(iv.to_string());
+        }
+
+        match self.slippage_tolerance {
+            Some(SlippageTolerance::TickSize(ticks)) => {
+                params["slippageToleranceType"] = json!("TickSize");
+                params["slippageTolerance"] = json!(ticks.to_string());
+            },
+            Some(SlippageTolerance::Percent(pct)) => {
+                params["slippageToleranceType"] = json!("Percent");
+                params["slippageTolerance"] = json!(pct.to_string());
+            },
+            None => {}
+        }
+
+        if let Some(tp) = self.take_profit {
+            params["takeProfit"] = json!(tp.to_string());
+        }
+
+        if let Some(sl) = self.stop_loss {
+            params["stopLoss"] = json!(sl.to_string());
+        }
+
+        if let Some(mode) = self.tpsl_mode {
+            params["tpslMode"] = json!(mode);
+        }
+
+        if let Some(trigger_by) = self.tp_trigger_by {
+            params["tpTriggerBy"] = json!(trigger_by);
+        }
+
+        if let Some(trigger_by) = self.sl_trigger_by {
+            params["slTriggerBy"] = json!(trigger_by);
+        }
+
+        if let Some(p) = self.tp_limit_price {
+            params["tpLimitPrice"] = json!(p.to_string());
+        }
+
+        if let Some(p) = self.sl_limit_price {
+            params["slLimitPrice"] = json!(p.to_string());
+        }
+
+        if let Some(order_type) = self.tp_order_type {
+            params["tpOrderType"] = json!(order_type);
+        }
+
+        if let Some(order_type) = self.sl_order_type {
+            params["slOrderType"] = json!(order_type);
+        }
+
+        if let Some(trigger_price) = self.trigger_price {
+            params["triggerPrice"] = json!(trigger_price.to_string());
+
+            if category.eq(&Category::Spot) {
+                params["orderFilter"] = json!(self.order_filter.unwrap_or(OrderFilter::StopOrder));
+            } else if let Some(direction) = self.trigger_direction {
+                params["triggerDirection"] = json!(direction as i32);
+            }
+        } else if let Some(filter) = self.order_filter {
+            params["orderFilter"] = json!(filter);
+        }
+
+        if let Some(trigger_by) = self.trigger_by {
+            params["triggerBy"] = json!(trigger_by);
+        }
+
+        if let Some(mmp) = self.mmp {
+            params["mmp"] = json!(mmp);
+        }
+
+        Ok(params)
+    }
+}
+
+// https://bybit-exchange.github.io/docs/v5/position/trading-stop
+// a price of 0 cancels the existing take profit / stop loss / trailing stop
+#[derive(Debug, Clone)]
+pub struct TradingStopRequest {
+    pub symbol: String,
+    pub position_idx: PositionIdx,
+    pub tpsl_mode: TpslMode,
+    pub take_profit: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub trailing_stop: Option<f64>,
+    pub active_price: Option<f64>,
+    pub tp_trigger_by: Option<TriggerBy>,
+    pub sl_trigger_by: Option<TriggerBy>,
+    pub tp_size: Option<f64>,
+    pub sl_size: Option<f64>,
+    pub tp_limit_price: Option<f64>,
+    pub sl_limit_price: Option<f64>,
+    pub tp_order_type: Option<OrderType>,
+    pub sl_order_type: Option<OrderType>,
 }
 
-impl TickerData {
-    pub fn annualized_funding(&self, funding_interval: u64) -> anyhow::Result<f64> {
-        let frequenzy = funding_interval as f64 / 60.0 / 24.0; // 4
-        let r = self.funding_rate.parse::<f64>()? * 365.0 / frequenzy;
-        Ok(r)
+impl TradingStopRequest {
+    pub fn new(symbol: &str, position_idx: PositionIdx, tpsl_mode: TpslMode) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            position_idx,
+            tpsl_mode,
+            take_profit: None,
+            stop_loss: None,
+            trailing_stop: None,
+            active_price: None,
+            tp_trigger_by: None,
+            sl_trigger_by: None,
+            tp_size: None,
+            sl_size: None,
+            tp_limit_price: None,
+            sl_limit_price: None,
+            tp_order_type: None,
+            sl_order_type: None,
+        }
+    }
+
+    pub fn take_profit(mut self, take_profit: f64) -> Self {
+        self.take_profit = Some(take_profit);
+        self
+    }
+
+    pub fn stop_loss(mut self, stop_loss: f64) -> Self {
+        self.stop_loss = Some(stop_loss);
+        self
+    }
+
+    /// Trailing distance in price units, optionally only activated once `active_price` trades.
+    pub fn trailing_stop(mut self, distance: f64, active_price: Option<f64>) -> Self {
+        self.trailing_stop = Some(distance);
+        self.active_price = active_price;
+        self
+    }
+
+    pub fn cancel_take_profit(self) -> Self {
+        self.take_profit(0.0)
     }
-}
 
-#[derive(Debug, Deserialize)]
-pub struct SpotTickerData {
-    #[serde(rename = "ask1Price", deserialize_with = "parse_string_to_f64")]
-    pub ask1_price: f64,
-    #[serde(rename = "ask1Size", deserialize_with = "parse_string_to_f64")]
-    pub ask1_size: f64,
-    #[serde(rename = "bid1Price", deserialize_with = "parse_string_to_f64")]
-    pub bid1_price: f64,
-    #[serde(rename = "bid1Size", deserialize_with = "parse_string_to_f64")]
-    pub bid1_size: f64,
-    #[serde(rename = "highPrice24h", deserialize_with = "parse_string_to_f64")]
-    pub high_price_24h: f64,
-    #[serde(rename = "lastPrice", deserialize_with = "parse_string_to_f64")]
-    pub last_price: f64,
-    #[serde(rename = "lowPrice24h", deserialize_with = "parse_string_to_f64")]
-    pub low_price_24h: f64,
-    pub symbol: String,
-    #[serde(rename = "turnover24h", deserialize_with = "parse_string_to_f64")]
-    pub turnover_24h: f64,
-    #[serde(rename = "volume24h", deserialize_with = "parse_string_to_f64")]
-    pub volume_24h: f64,
-}
+    pub fn cancel_stop_loss(self) -> Self {
+        self.stop_loss(0.0)
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct OptionTickerData {
-    // ───── level-1 quotes ─────
-    #[serde(rename = "ask1Iv",   deserialize_with = "parse_string_to_f64")]
-    pub ask1_iv: f64,
-    #[serde(rename = "ask1Price", deserialize_with = "parse_string_to_f64")]
-    pub ask1_price: f64,
-    #[serde(rename = "ask1Size",  deserialize_with = "parse_string_to_f64")]
-    pub ask1_size: f64,
+    pub fn cancel_trailing_stop(self) -> Self {
+        self.trailing_stop(0.0, None)
+    }
 
-    #[serde(rename = "bid1Iv",    deserialize_with = "parse_string_to_f64")]
-    pub bid1_iv: f64,
-    #[serde(rename = "bid1Price", deserialize_with = "parse_string_to_f64")]
-    pub bid1_price: f64,
-    #[serde(rename = "bid1Size",  deserialize_with = "parse_string_to_f64")]
-    pub bid1_size: f64,
+    pub fn tp_trigger_by(mut self, trigger_by: TriggerBy) -> Self {
+        self.tp_trigger_by = Some(trigger_by);
+        self
+    }
 
+    pub fn sl_trigger_by(mut self, trigger_by: TriggerBy) -> Self {
+        self.sl_trigger_by = Some(trigger_by);
+        self
+    }
 
-    // ───── basic greeks & mark ─────
-    #[serde(rename = "delta",     deserialize_with = "parse_string_to_f64")]
-    pub delta: f64,
-    #[serde(rename = "gamma",     deserialize_with = "parse_string_to_f64")]
-    pub gamma: f64,
-    #[serde(rename = "theta",     deserialize_with = "parse_string_to_f64")]
-    pub theta: f64,
-    #[serde(rename = "vega",      deserialize_with = "parse_string_to_f64")]
-    pub vega: f64,
-    #[serde(rename = "markIv",    deserialize_with = "parse_string_to_f64")]
-    pub mark_iv: f64,
-    #[serde(rename = "markPrice", deserialize_with = "parse_string_to_f64")]
-    pub mark_price: f64,
+    pub fn tp_size(mut self, size: f64) -> Self {
+        self.tp_size = Some(size);
+        self
+    }
 
-    // ───── spot / index linkage ─────
-    #[serde(rename = "indexPrice",       deserialize_with = "parse_string_to_f64")]
-    pub index_price: f64,
-    #[serde(rename = "underlyingPrice",  deserialize_with = "parse_string_to_f64")]
-    pub underlying_price: f64,
-    #[serde(rename = "predictedDeliveryPrice", deserialize_with = "parse_string_to_f64")]
-    pub predicted_delivery_price: f64,
+    pub fn sl_size(mut self, size: f64) -> Self {
+        self.sl_size = Some(size);
+        self
+    }
 
+    pub fn tp_limit(mut self, limit_price: f64) -> Self {
+        self.tp_order_type = Some(OrderType::Limit);
+        self.tp_limit_price = Some(limit_price);
+        self
+    }
 
-    // ───── 24-h stats ─────
-    #[serde(rename = "change24h",     deserialize_with = "parse_string_to_f64")]
-    pub change_24h: f64,
-    #[serde(rename = "highPrice24h",  deserialize_with = "parse_string_to_f64")]
-    pub high_price_24h: f64,
-    #[serde(rename = "lowPrice24h",   deserialize_with = "parse_string_to_f64")]
-    pub low_price_24h: f64,
-    #[serde(rename = "turnover24h",   deserialize_with = "parse_string_to_f64")]
-    pub turnover_24h: f64,
-    #[serde(rename = "volume24h",     deserialize_with = "parse_string_to_f64")]
-    pub volume_24h: f64,
+    pub fn sl_limit(mut self, limit_price: f64) -> Self {
+        self.sl_order_type = Some(OrderType::Limit);
+        self.sl_limit_price = Some(limit_price);
+        self
+    }
 
-    // ───── running totals ─────
-    #[serde(rename = "totalTurnover", deserialize_with = "parse_string_to_f64")]
-    pub total_turnover: f64,
-    #[serde(rename = "totalVolume",   deserialize_with = "parse_string_to_f64")]
-    pub total_volume: f64,
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.take_profit.is_some() || self.stop_loss.is_some() || self.trailing_stop.is_some(),
+            "trading stop needs a take_profit, stop_loss or trailing_stop"
+        );
 
-    // ───── position-level ─────
-    #[serde(rename = "openInterest", deserialize_with = "parse_string_to_f64")]
-    pub open_interest: f64,
+        for (name, value) in [("take_profit", self.take_profit), ("stop_loss", self.stop_loss), ("trailing_stop", self.trailing_stop)] {
+            if let Some(v) = value {
+                ensure!(v >= 0.0, "{name} must not be negative");
+            }
+        }
 
-    // ───── anything that is already a string ─────
-    pub symbol: String,
-}
+        match self.tpsl_mode {
+            TpslMode::Full => {
+                ensure!(self.tp_size.is_none() && self.sl_size.is_none(), "tp_size/sl_size are only valid in Partial tpsl_mode");
+                ensure!(self.tp_order_type.is_none() && self.sl_order_type.is_none(), "limit take_profit/stop_loss requires Partial tpsl_mode");
+            },
+            TpslMode::Partial => {
+                // bybit rejects partial tp/sl unless both sizes are given and equal
+                match (self.tp_size, self.sl_size) {
+                    (Some(tp_size), Some(sl_size)) => {
+                        ensure!(tp_size > 0.0, "tp_size must be positive");
+                        ensure!(tp_size == sl_size, "tp_size and sl_size must be equal in Partial tpsl_mode");
+                    },
+                    _ => bail!("Partial tpsl_mode requires both tp_size and sl_size"),
+                }
+            }
+        }
 
-pub struct NewOrder {
-    pub symbol: String,
-    pub side: TradeDirection,
-    pub qty: f64,
-    pub order_type: OrderType,
-    pub price: Option<f64>,
-    pub time_in_force: Option<TimeInForce>
-}
+        ensure!(self.take_profit.is_some() || self.tp_order_type.is_none(), "tp_limit_price given without take_profit");
+        ensure!(self.stop_loss.is_some() || self.sl_order_type.is_none(), "sl_limit_price given without stop_loss");
+
+        Ok(())
+    }
 
-impl NewOrder {
+    /// Validates and serializes the request, without the `category` field.
     pub fn into_json(&self) -> anyhow::Result<serde_json::Value> {
 
-        if self.order_type.eq(&OrderType::Limit) {
-            ensure!(self.price.is_some(), "create_order() missing price");
-        }
+        self.validate()?;
 
         let mut params = json!({
             "symbol": self.symbol,
-            "side": self.side,
-            "qty": self.qty.to_string(),
-            "orderType": self.order_type,
+            "positionIdx": self.position_idx,
+            "tpslMode": self.tpsl_mode,
         });
 
-        if let Some(p) = self.price {
-            params["price"] = json!(p.to_string());
+        let prices = [
+            ("takeProfit", self.take_profit),
+            ("stopLoss", self.stop_loss),
+            ("trailingStop", self.trailing_stop),
+            ("activePrice", self.active_price),
+            ("tpSize", self.tp_size),
+            ("slSize", self.sl_size),
+            ("tpLimitPrice", self.tp_limit_price),
+            ("slLimitPrice", self.sl_limit_price),
+        ];
+
+        for (key, value) in prices {
+            if let Some(v) = value {
+                params[key] = json!(v.to_string());
+            }
         }
 
-        if let Some(tip) = self.time_in_force {
-            params["timeInForce"] = json!(tip);
+        if let Some(trigger_by) = self.tp_trigger_by {
+            params["tpTriggerBy"] = json!(trigger_by);
+        }
+
+        if let Some(trigger_by) = self.sl_trigger_by {
+            params["slTriggerBy"] = json!(trigger_by);
+        }
+
+        if let Some(order_type) = self.tp_order_type {
+            params["tpOrderType"] = json!(order_type);
+        }
+
+        if let Some(order_type) = self.sl_order_type {
+            params["slOrderType"] = json!(order_type);
         }
 
         Ok(params)
@@ -550,9 +2524,10 @@ impl NewOrder {
 #[cfg(test)]
 mod tests {
 
-    use crate::{BybitResponse, CreateOrderResponse};
+    use crate::{AccountInfo, AccountSettings, AccountType, ApiKeyInfo, ApiKeySettings, ApiPermission, ApiPermissions, BybitResponse, CancelledOrder, Category, ClosedPnl, CoinBalance, CoinHolding, CoinMetadata, CollateralInfo, CollateralRatioInfo, ConvertAccountType, ConvertQuote, ConvertRecord, ConvertStatus, CreateOrderResponse, DepositAddresses, DepositRecord, DepositStatus, ExecType, Execution, FeeRate, ForceChain, InternalDepositRecord, InternalDepositStatus, LeverageFilter, MarginMode, MarketUnit, MmpConfig, MmpState, Order, OrderCostEstimate, OrderRequest, PositionIdx, PositionInfo, PositionSide, PreflightPolicy, PreflightReport, SpotMarginState, SubApiKey, SubMember, SubMemberStatus, SubMemberType, TimeInForce, TpslMode, TradeDirection, TradingStopRequest, TransactionLog, TransactionType, TransferReceipt, TransferRecord, TransferStatus, TriggerBy, TriggerDirection, UpgradeStatus, VipMarginData, WithdrawAccountType, WithdrawRequest, WithdrawStatus, WithdrawalPolicy, WithdrawalRecord, net_positions};
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     pub fn test_response_decoding() {
         let json = r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"xxxx","orderLinkId":""},"retExtInfo":{},"time":1722030653718}"#;
 
@@ -568,4 +2543,517 @@ mod tests {
         dbg!(&resp);
     }
 
-}
\ No newline at end of file
+    #[test]
+    pub fn test_execution_decoding() {
+        let json = r#"{"symbol":"ETHUSDT","orderType":"Limit","underlyingPrice":"","orderLinkId":"","orderId":"1d1a6e4e-7e6b-4bb6-8bc3-9a1d0fa7c2a3","stopOrderType":"UNKNOWN","execTime":"1722031260541","feeCurrency":"USDT","feeRate":"0.0002","tradeIv":"","blockTradeId":"","markPrice":"3261.49","execPrice":"3260.5","markIv":"","orderQty":"0.1","orderPrice":"3260.5","execValue":"326.05","closedSize":"0","execType":"Trade","seq":4688002127,"side":"Buy","indexPrice":"","leavesQty":"0","isMaker":true,"execFee":"0.06521","execId":"c4b7e4b5-9a8e-5d0b-b6f0-5b1f1d7c9b41","execQty":"0.1","extraFees":""}"#;
+
+        let execution: Execution = serde_json::from_str(json).unwrap();
+        assert_eq!(execution.exec_type, ExecType::Trade);
+        assert_eq!(execution.exec_price, 3260.5);
+        assert_eq!(execution.fee_rate, Some(0.0002));
+        assert_eq!(execution.trade_iv, None);
+        assert!(execution.is_maker);
+    }
+
+    #[test]
+    pub fn test_closed_pnl_decoding() {
+        let json = r#"{"symbol":"ETHUSDT","orderType":"Market","leverage":"10","updatedTime":"1722033457925","side":"Sell","orderId":"98d4a3c5-5b3c-4b6e-8cd2-1c7d6c2e4a01","closedPnl":"-1.20564","avgEntryPrice":"3260.5","qty":"0.1","cumEntryValue":"326.05","createdTime":"1722033457921","orderPrice":"3097.5","closedSize":"0.1","avgExitPrice":"3259.2","execType":"Trade","fillCount":"1","cumExitValue":"325.92"}"#;
+
+        let closed: ClosedPnl = serde_json::from_str(json).unwrap();
+        assert_eq!(closed.side, TradeDirection::Sell);
+        assert_eq!(closed.closed_pnl, -1.20564);
+        assert_eq!(closed.open_fee, None);
+    }
+
+    #[test]
+    pub fn test_order_request_validation() {
+        let market = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1);
+        assert!(market.validate(Category::Linear).is_ok());
+        assert!(market.clone().price(3000.0).validate(Category::Linear).is_err());
+        assert!(market.clone().time_in_force(TimeInForce::PostOnly).validate(Category::Linear).is_err());
+        assert!(market.clone().market_unit(MarketUnit::QuoteCoin).validate(Category::Linear).is_err());
+        assert!(market.clone().market_unit(MarketUnit::QuoteCoin).validate(Category::Spot).is_ok());
+
+        let limit = OrderRequest::new("ETHUSDT", TradeDirection::Sell, crate::OrderType::Limit, 0.1);
+        assert!(limit.validate(Category::Linear).is_err());
+
+        let rpi = OrderRequest::limit("ETHUSDT", TradeDirection::Sell, 0.1, 3000.0).time_in_force(TimeInForce::RPI).reduce_only(true);
+        assert!(rpi.validate(Category::Linear).is_ok());
+        assert!(rpi.validate(Category::Spot).is_err());
+
+        let option = OrderRequest::limit("ETH-27SEP24-3000-C", TradeDirection::Buy, 1.0, 50.0);
+        assert!(option.validate(Category::Option).is_err());
+        assert!(option.order_link_id("opt-1").validate(Category::Option).is_ok());
+    }
+
+    #[test]
+    pub fn test_order_request_json() {
+        let order = OrderRequest::limit("ETHUSDT", TradeDirection::Buy, 0.1, 3000.5)
+            .time_in_force(TimeInForce::PostOnly)
+            .order_link_id("my-order-1")
+            .reduce_only(true)
+            .position_idx(PositionIdx::HedgeBuy);
+
+        let json = order.into_json(Category::Linear).unwrap();
+        assert_eq!(json["price"], "3000.5");
+        assert_eq!(json["timeInForce"], "PostOnly");
+        assert_eq!(json["orderLinkId"], "my-order-1");
+        assert_eq!(json["reduceOnly"], true);
+        assert_eq!(json["positionIdx"], 1);
+        assert!(json.get("category").is_none());
+    }
+
+    #[test]
+    pub fn test_order_request_tpsl() {
+        let full = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1)
+            .take_profit(4000.0)
+            .stop_loss(2500.0)
+            .tp_trigger_by(TriggerBy::MarkPrice);
+        let json = full.into_json(Category::Linear).unwrap();
+        assert_eq!(json["takeProfit"], "4000");
+        assert_eq!(json["tpTriggerBy"], "MarkPrice");
+
+        let partial_limit = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1).take_profit(4000.0).tp_limit(4001.0);
+        assert!(partial_limit.validate(Category::Linear).is_err());
+        assert!(partial_limit.tpsl_mode(TpslMode::Partial).validate(Category::Linear).is_ok());
+
+        let orphan = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1).sl_trigger_by(TriggerBy::LastPrice).take_profit(4000.0);
+        assert!(orphan.validate(Category::Linear).is_err());
+
+        let spot_market = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 0.1).take_profit(4000.0);
+        assert!(spot_market.validate(Category::Spot).is_err());
+    }
+
+    #[test]
+    pub fn test_trading_stop_request() {
+        assert!(TradingStopRequest::new("ETHUSDT", PositionIdx::OneWay, TpslMode::Full).validate().is_err());
+
+        let partial = TradingStopRequest::new("ETHUSDT", PositionIdx::HedgeBuy, TpslMode::Partial).take_profit(4000.0);
+        assert!(partial.validate().is_err());
+
+        // both sizes are required and must match
+        assert!(partial.clone().tp_size(0.05).validate().is_err());
+        assert!(partial.clone().tp_size(0.05).sl_size(0.04).validate().is_err());
+
+        let json = partial.tp_size(0.05).sl_size(0.05).tp_limit(3990.0).into_json().unwrap();
+        assert_eq!(json["tpSize"], "0.05");
+        assert_eq!(json["tpOrderType"], "Limit");
+        assert_eq!(json["positionIdx"], 1);
+
+        let json = TradingStopRequest::new("ETHUSDT", PositionIdx::OneWay, TpslMode::Full).cancel_stop_loss().trailing_stop(25.0, None).into_json().unwrap();
+        assert_eq!(json["stopLoss"], "0");
+        assert_eq!(json["trailingStop"], "25");
+        assert!(json.get("activePrice").is_none());
+    }
+
+    #[test]
+    pub fn test_order_decoding() {
+        let json = r#"{"orderId":"fd4300ae-7847-404e-b947-b46980a4d140","orderLinkId":"test-000005","blockTradeId":"","symbol":"ETHUSDT","price":"1600.00","qty":"0.10","side":"Buy","isLeverage":"","positionIdx":1,"orderStatus":"New","cancelType":"UNKNOWN","rejectReason":"EC_NoError","avgPrice":"0","leavesQty":"0.10","leavesValue":"160","cumExecQty":"0.00","cumExecValue":"0","cumExecFee":"0","timeInForce":"GTC","orderType":"Limit","stopOrderType":"UNKNOWN","orderIv":"","triggerPrice":"0.00","takeProfit":"2500.00","stopLoss":"1500.00","tpTriggerBy":"LastPrice","slTriggerBy":"LastPrice","triggerDirection":0,"triggerBy":"UNKNOWN","lastPriceOnCreated":"","reduceOnly":false,"closeOnTrigger":false,"smpType":"None","smpGroup":0,"smpOrderId":"","tpslMode":"Full","tpLimitPrice":"","slLimitPrice":"","placeType":"","createType":"CreateByUser","marketUnit":"","createdTime":"1684738540559","updatedTime":"1684738540561"}"#;
+
+        let order: Order = serde_json::from_str(json).unwrap();
+        assert_eq!(order.take_profit, Some(2500.0));
+        assert_eq!(order.tp_trigger_by, Some(TriggerBy::LastPrice));
+        assert_eq!(order.tpsl_mode, Some(TpslMode::Full));
+        assert_eq!(order.tp_limit_price, None);
+        assert_eq!(order.trigger_direction, None);
+
+        let stop: Order = serde_json::from_str(&json.replace(r#""triggerDirection":0"#, r#""triggerDirection":2"#)).unwrap();
+        assert_eq!(stop.trigger_direction, Some(TriggerDirection::FallsTo));
+        assert!(serde_json::from_str::<Order>(&json.replace(r#""triggerDirection":0"#, r#""triggerDirection":5"#)).is_err());
+    }
+
+    #[test]
+    pub fn test_conditional_order_request() {
+        let stop = OrderRequest::stop_market("ETHUSDT", TradeDirection::Sell, 0.1, 2900.0, TriggerDirection::FallsTo)
+            .trigger_by(TriggerBy::MarkPrice)
+            .reduce_only(true);
+        let json = stop.into_json(Category::Linear).unwrap();
+        assert_eq!(json["triggerPrice"], "2900");
+        assert_eq!(json["triggerDirection"], 2);
+        assert_eq!(json["triggerBy"], "MarkPrice");
+        assert!(json.get("orderFilter").is_none());
+
+        let spot = OrderRequest::stop_limit("ETHUSDT", TradeDirection::Buy, 0.1, 3110.0, 3100.0, TriggerDirection::RisesTo);
+        let json = spot.into_json(Category::Spot).unwrap();
+        assert_eq!(json["orderFilter"], "StopOrder");
+        assert!(json.get("triggerDirection").is_none());
+
+        let spot_trigger_by = OrderRequest::stop_market("ETHUSDT", TradeDirection::Buy, 0.1, 3100.0, TriggerDirection::RisesTo).trigger_by(TriggerBy::LastPrice);
+        assert!(spot_trigger_by.validate(Category::Spot).is_err());
+
+        let option = OrderRequest::stop_limit("ETH-27SEP24-3000-C", TradeDirection::Buy, 1.0, 50.0, 45.0, TriggerDirection::RisesTo).order_link_id("opt-1");
+        assert!(option.validate(Category::Option).is_err());
+    }
+
+    #[test]
+    pub fn test_leverage_filter_check() {
+        let filter: LeverageFilter = serde_json::from_str(r#"{"minLeverage":"1","maxLeverage":"100.00","leverageStep":"0.01"}"#).unwrap();
+        assert!(filter.check(1.0).is_ok());
+        assert!(filter.check(12.34).is_ok());
+        assert!(filter.check(100.0).is_ok());
+        assert!(filter.check(0.5).is_err());
+        assert!(filter.check(100.01).is_err());
+        assert!(filter.check(12.345).is_err());
+    }
+
+    #[test]
+    pub fn test_hedge_position_decoding() {
+        let json = r#"[{"positionIdx":1,"riskId":1,"riskLimitValue":"2000000","symbol":"ETHUSDT","side":"Buy","size":"0.30","avgPrice":"3000","positionValue":"900","tradeMode":0,"positionStatus":"Normal","autoAddMargin":0,"adlRankIndicator":2,"leverage":"10","positionBalance":"90.1","markPrice":"3010","liqPrice":"","bustPrice":"","positionMM":"5.4","positionIM":"90","tpslMode":"Full","takeProfit":"","stopLoss":"2800","trailingStop":"0","unrealisedPnl":"3","curRealisedPnl":"-0.5","cumRealisedPnl":"12","seq":4688002127,"isReduceOnly":false,"mmrSysUpdatedTime":"","leverageSysUpdatedTime":"","sessionAvgPrice":"","createdTime":"1676538056258","updatedTime":"1697673600012"},
+            {"positionIdx":2,"riskId":1,"riskLimitValue":"2000000","symbol":"ETHUSDT","side":"Sell","size":"0.10","avgPrice":"3020","positionValue":"302","tradeMode":0,"positionStatus":"Normal","autoAddMargin":0,"adlRankIndicator":2,"leverage":"10","positionBalance":"30.2","markPrice":"3010","liqPrice":"","bustPrice":"","positionMM":"1.8","positionIM":"30.2","tpslMode":"Full","takeProfit":"","stopLoss":"","trailingStop":"0","unrealisedPnl":"1","curRealisedPnl":"-0.2","cumRealisedPnl":"3","seq":4688002128,"isReduceOnly":false,"mmrSysUpdatedTime":"","leverageSysUpdatedTime":"","sessionAvgPrice":"","createdTime":"1676538056258","updatedTime":"1697673600012"},
+            {"positionIdx":0,"riskId":1,"riskLimitValue":"2000000","symbol":"BTCUSDT","side":"","size":"0","avgPrice":"0","positionValue":"0","tradeMode":0,"positionStatus":"Normal","autoAddMargin":0,"adlRankIndicator":0,"leverage":"10","positionBalance":"0","markPrice":"60000","liqPrice":"","bustPrice":"","positionMM":"0","positionIM":"0","tpslMode":"Full","takeProfit":"","stopLoss":"","trailingStop":"0","unrealisedPnl":"0","curRealisedPnl":"0","cumRealisedPnl":"0","seq":-1,"isReduceOnly":false,"mmrSysUpdatedTime":"","leverageSysUpdatedTime":"","sessionAvgPrice":"","createdTime":"1676538056258","updatedTime":"1697673600012"}]"#;
+
+        let positions: Vec<PositionInfo> = serde_json::from_str(json).unwrap();
+        assert_eq!(positions[0].position_idx, PositionIdx::HedgeBuy);
+        assert_eq!(positions[1].side, PositionSide::Sell);
+        assert_eq!(positions[2].side, PositionSide::None);
+        assert_eq!(positions[0].stop_loss, Some(2800.0));
+
+        // option positions and portfolio margin leave IM/MM empty, other numbers must still parse
+        let option_json = json.replace(r#""positionIM":"0""#, r#""positionIM":"""#).replace(r#""positionMM":"0""#, r#""positionMM":"""#);
+        let option_positions: Vec<PositionInfo> = serde_json::from_str(&option_json).unwrap();
+        assert_eq!(option_positions[2].position_im, None);
+        assert_eq!(option_positions[2].position_mm, None);
+        let bad_json = json.replace(r#""markPrice":"60000""#, r#""markPrice":"""#);
+        assert!(serde_json::from_str::<Vec<PositionInfo>>(&bad_json).is_err());
+
+        let net = net_positions(positions.iter());
+        assert!((net["ETHUSDT"] - 0.2).abs() < 1e-9);
+        assert_eq!(net["BTCUSDT"], 0.0);
+    }
+
+    #[test]
+    pub fn test_cancel_all_decoding() {
+        let json = r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"orderId":"1616024329462743808","orderLinkId":"1616024329462743809"},{"orderId":"1616024287544869632","orderLinkId":""}],"success":"1"},"retExtInfo":{},"time":1722029752786}"#;
+
+        let resp: BybitResponse = serde_json::from_str(json).unwrap();
+        let cancelled: Vec<CancelledOrder> = serde_json::from_value(resp.result["list"].clone()).unwrap();
+        assert_eq!(cancelled.len(), 2);
+        assert_eq!(cancelled[0].order_link_id, "1616024329462743809");
+    }
+
+    #[test]
+    pub fn test_spot_margin_decoding() {
+        let state: SpotMarginState = serde_json::from_str(r#"{"spotLeverage":"4","spotMarginMode":"1","effectiveLeverage":"1"}"#).unwrap();
+        assert!(state.spot_margin_mode);
+        assert_eq!(state.spot_leverage, 4.0);
+
+        let json = r#"[{"list":[{"borrowable":true,"collateralRatio":"0.95","currency":"BTC","hourlyBorrowRate":"0.0000015021220000","liquidationOrder":11,"marginCollateral":true,"maxBorrowingAmount":"3"}],"vipLevel":"No VIP"}]"#;
+        let data: Vec<VipMarginData> = serde_json::from_str(json).unwrap();
+        assert_eq!(data[0].list[0].collateral_ratio, 0.95);
+
+        let json = r#"[{"currency":"BTC","collateralRatioList":[{"maxQty":"1000000","minQty":"0","collateralRatio":"0.85"},{"maxQty":"","minQty":"1000000","collateralRatio":"0"}]}]"#;
+        let tiers: Vec<CollateralRatioInfo> = serde_json::from_str(json).unwrap();
+        assert_eq!(tiers[0].collateral_ratio_list[1].max_qty, None);
+    }
+
+    #[test]
+    pub fn test_fee_rate_and_cost() {
+        let json = r#"[{"symbol":"ETHUSDT","takerFeeRate":"0.00055","makerFeeRate":"0.0002"},{"symbol":"","baseCoin":"BTC","takerFeeRate":"0.0003","makerFeeRate":"0.0003"}]"#;
+        let rates: Vec<FeeRate> = serde_json::from_str(json).unwrap();
+        assert_eq!(rates[0].key(), "ETHUSDT");
+        assert_eq!(rates[1].key(), "BTC");
+
+        let taker = OrderRequest::market("ETHUSDT", TradeDirection::Buy, 2.0);
+        let cost = OrderCostEstimate::new(Category::Linear, &taker, &rates[0], Some(3000.0), None).unwrap();
+        assert_eq!(cost.notional, 6000.0);
+        assert!((cost.fee - 3.3).abs() < 1e-9);
+        assert!(!cost.is_maker);
+
+        let maker = OrderRequest::limit("ETHUSDT", TradeDirection::Buy, 2.0, 2500.0).time_in_force(TimeInForce::PostOnly);
+        let cost = OrderCostEstimate::new(Category::Linear, &maker, &rates[0], None, None).unwrap();
+        assert!((cost.fee - 1.0).abs() < 1e-9);
+
+        // a resting GTC limit is taker unless told otherwise
+        let resting = OrderRequest::limit("ETHUSDT", TradeDirection::Buy, 2.0, 2500.0);
+        assert!(!OrderCostEstimate::new(Category::Linear, &resting, &rates[0], None, None).unwrap().is_maker);
+        let cost = OrderCostEstimate::new(Category::Linear, &resting, &rates[0], None, Some(true)).unwrap();
+        assert!(cost.is_maker);
+        assert!((cost.fee - 1.0).abs() < 1e-9);
+
+        assert!(OrderCostEstimate::new(Category::Linear, &taker, &rates[0], None, None).is_err());
+        assert!(OrderCostEstimate::new(Category::Linear, &taker, &rates[0], Some(3000.0), Some(true)).is_err());
+        assert!(OrderCostEstimate::new(Category::Option, &maker, &rates[1], None, None).is_err());
+    }
+
+    #[test]
+    pub fn test_account_log_decoding() {
+        let json = r#"{"id":"592324_XRPUSDT_161440249321","symbol":"XRPUSDT","category":"linear","side":"Buy","transactionTime":"1714147200000","type":"SETTLEMENT","qty":"0","size":"-100","currency":"USDT","tradePrice":"0.5191","funding":"-0.0130","fee":"","cashFlow":"0","change":"-0.013","cashBalance":"1001.55","feeRate":"","bonusChange":"","tradeId":"","orderId":"","orderLinkId":""}"#;
+        let log: TransactionLog = serde_json::from_str(json).unwrap();
+        assert_eq!(log.transaction_type, TransactionType::Settlement);
+        assert_eq!(log.funding, -0.013);
+        assert_eq!(log.fee, 0.0);
+        assert_eq!(log.fee_rate, None);
+
+        let json = r#"{"marginMode":"REGULAR_MARGIN","updatedTime":"1697078946000","unifiedMarginStatus":5,"dcpStatus":"OFF","timeWindow":10,"smpGroup":0,"isMasterTrader":false,"spotHedgingStatus":"OFF"}"#;
+        let settings: AccountSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.margin_mode, MarginMode::RegularMargin);
+        assert!(settings.is_uta2());
+        assert!(!settings.dcp_status);
+    }
+
+    #[test]
+    pub fn test_account_info_decoding() {
+        let json = r#"{"totalEquity":"3.31216591","accountIMRate":"0.1","accountMMRate":"0.02","totalMarginBalance":"3.00326056","totalInitialMargin":"0.300326056","accountType":"UNIFIED","totalAvailableBalance":"2.702934504","accountLTV":"","totalMaintenanceMargin":"0.0600652112","totalPerpUPL":"0","totalWalletBalance":"3.00326056","coin":[{"availableToBorrow":"","bonus":"0","accruedInterest":"0","availableToWithdraw":"","totalOrderIM":"0","equity":"0.1","totalPositionMM":"0","usdValue":"3000","unrealisedPnl":"0","collateralSwitch":true,"spotHedgingQty":"0","borrowAmount":"","totalPositionIM":"0","walletBalance":"0.1","cumRealisedPnl":"0","locked":"0","marginCollateral":true,"coin":"ETH"}]}"#;
+        let info: AccountInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.account_ltv, None);
+        assert_eq!(info.margin_ratio(), Some(0.02));
+        assert_eq!(info.free_collateral(), Some(2.702934504));
+        assert!((info.liquidation_buffer().unwrap() - 2.9431953488).abs() < 1e-9);
+
+        let eth = info.coin("ETH").unwrap();
+        assert_eq!(eth.borrow_amount, 0.0);
+        assert_eq!(eth.available_to_withdraw, None);
+        assert_eq!(eth.free_balance(), 0.1);
+
+        // isolated margin leaves the account-level figures empty
+        let json = r#"{"totalEquity":"10","accountIMRate":"","accountMMRate":"","totalMarginBalance":"","totalInitialMargin":"","accountType":"UNIFIED","totalAvailableBalance":"","accountLTV":"","totalMaintenanceMargin":"","totalPerpUPL":"0","totalWalletBalance":"10","coin":[]}"#;
+        let info: AccountInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.margin_ratio(), None);
+        assert_eq!(info.free_collateral(), None);
+    }
+
+    #[test]
+    pub fn test_coin_balance_holdings() {
+        let json = r#"[{"coin":"USDT","walletBalance":"100.5","transferBalance":"100.5","bonus":"0","transferSafeAmount":""},{"coin":"BTC","walletBalance":"0.01","transferBalance":"0.01","bonus":""}]"#;
+        let balances: Vec<CoinBalance> = serde_json::from_str(json).unwrap();
+        assert_eq!(balances[0].transfer_safe_amount, None);
+        assert_eq!(balances[1].bonus, 0.0);
+
+        let mut usdt = CoinHolding::new("USDT");
+        usdt.add(AccountType::FUND, balances[0].wallet_balance);
+        usdt.add(AccountType::UNIFIED, 50.0);
+        usdt.add(AccountType::UNIFIED, 25.0);
+        assert_eq!(usdt.total, 175.5);
+        assert_eq!(usdt.by_account[&AccountType::UNIFIED], 75.0);
+    }
+
+    #[test]
+    pub fn test_collateral_and_upgrade_decoding() {
+        let json = r#"[{"availableToBorrow":"3","freeBorrowingAmount":"","freeBorrowAmount":"0","maxBorrowingAmount":"3","hourlyBorrowRate":"0.00000147","borrowUsageRate":"0.01","collateralSwitch":true,"borrowAmount":"0.01","borrowable":true,"currency":"BTC","otherBorrowAmount":"0","marginCollateral":true,"freeBorrowingLimit":"0","collateralRatio":"0.95"}]"#;
+        let info: Vec<CollateralInfo> = serde_json::from_str(json).unwrap();
+        assert!(info[0].collateral_switch);
+        assert_eq!(info[0].collateral_ratio, 0.95);
+
+        let json = r#"{"unifiedUpdateStatus":"FAIL","unifiedUpdateMsg":{"msg":["Please cancel all open orders before upgrading."]}}"#;
+        let status: UpgradeStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(status.messages().len(), 1);
+    }
+
+    #[test]
+    pub fn test_mmp() {
+        let json = r#"{"baseCoin":"BTC","mmpEnabled":true,"window":"5000","frozenPeriod":"100000","qtyLimit":"0.01","deltaLimit":"0.01","mmpFrozenUntil":"1675760625519","mmpFrozen":false}"#;
+        let state: MmpState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.config(), MmpConfig::new("BTC", 5000, 100000, 0.01, 0.01));
+        assert_eq!(state.config().into_json().unwrap()["window"], "5000");
+        assert!(MmpConfig::new("BTC", 0, 0, 1.0, 1.0).validate().is_err());
+
+        // an empty or missing value is a bad payload, not a zero window
+        assert!(serde_json::from_str::<MmpState>(&json.replace(r#""1675760625519""#, r#""""#)).is_err());
+        assert!(serde_json::from_str::<MmpState>(&json.replace(r#""5000""#, "null")).is_err());
+
+        let quote = OrderRequest::limit("BTC-27SEP24-60000-C", TradeDirection::Sell, 0.1, 2500.0).order_link_id("q-1").mmp(true);
+        assert_eq!(quote.into_json(Category::Option).unwrap()["mmp"], true);
+        assert!(quote.validate(Category::Linear).is_err());
+    }
+
+    #[test]
+    pub fn test_transfer_decoding() {
+        let receipt: TransferReceipt = serde_json::from_str(r#"{"transferId":"42c0cfb0-6bca-c242-bc76-4e6df6cbcb16","status":"SUCCESS"}"#).unwrap();
+        assert!(receipt.status.is_final());
+
+        let receipt: TransferReceipt = serde_json::from_str(r#"{"transferId":"42c0cfb0-6bca-c242-bc76-4e6df6cbcb16"}"#).unwrap();
+        assert_eq!(receipt.status, TransferStatus::StatusUnknown);
+        assert!(!receipt.status.is_final());
+
+        let json = r#"{"transferId":"selfTransfer_a1091cc7-9364-4b74-8de1-18f02c6f2d5c","coin":"USDT","amount":"5000","fromMemberId":"1111","toMemberId":"1111","fromAccountType":"SPOT","toAccountType":"UNIFIED","timestamp":"1658986298000","status":"SUCCESS"}"#;
+        let record: TransferRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(record.amount, 5000.0);
+        assert_eq!(record.status, TransferStatus::Success);
+
+        assert!(crate::utils::is_uuid("42c0cfb0-6bca-c242-bc76-4e6df6cbcb16"));
+        assert!(!crate::utils::is_uuid("42c0cfb0-6bca-c242-bc76-4e6df6cbcb1g"));
+        assert!(!crate::utils::is_uuid("my-transfer"));
+    }
+
+    #[test]
+    pub fn test_deposit_withdrawal_decoding() {
+        let json = r#"{"coin":"USDT","chain":"ETH","amount":"10000","txID":"skip-notification-scene-test-amount-202212270944-533285-USDT","status":3,"toAddress":"test-amount-address","tag":"","depositFee":"","successAt":"1672134274000","confirmations":"10000","txIndex":"","blockHash":"","batchReleaseLimit":"-1","depositType":"0"}"#;
+        let deposit: DepositRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(deposit.status, DepositStatus::Success);
+        assert_eq!(deposit.deposit_fee, 0.0);
+
+        let json = r#"{"id":"1103","amount":"0.1","type":1,"coin":"ETH","address":"xxxx***@gmail.com","status":2,"createdTime":"1705393280","txID":"77c37e5c-d9fa-41e5-bd13-c9b59d95"}"#;
+        let internal: InternalDepositRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(internal.status, InternalDepositStatus::Success);
+
+        let json = r#"{"coin":"USDT","chain":"ETH","amount":"18","txID":"","status":"success","toAddress":"0x99ced335e1d6bc26e8c2d0f3c6c6c0a1c3f8e3b1","tag":"","withdrawFee":"5","createTime":"1670922217000","updateTime":"1670922233000","withdrawId":"9976","withdrawType":0}"#;
+        let withdrawal: WithdrawalRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(withdrawal.status, WithdrawStatus::Success);
+        assert_eq!(withdrawal.withdraw_fee, 5.0);
+
+        let status: WithdrawStatus = serde_json::from_str(r#""SomethingNew""#).unwrap();
+        assert_eq!(status, WithdrawStatus::Unknown);
+    }
+
+    #[test]
+    pub fn test_withdrawal_policy() {
+        let now = 1_700_000_000_000;
+        let address = "0x99ced335e1d6bc26e8c2d0f3c6c6c0a1c3f8e3b1";
+
+        let mut policy = WithdrawalPolicy::new()
+            .allow("USDT", "ETH", address)
+            .daily_limit("USDT", 1000.0);
+
+        // not allowlisted
+        assert!(policy.check(&WithdrawRequest::new("USDT", "TRX", address, 10.0), now).is_err());
+        assert!(policy.check(&WithdrawRequest::new("USDT", "ETH", "0xdeadbeef", 10.0), now).is_err());
+
+        // no limit configured for the coin
+        let policy_no_limit = WithdrawalPolicy::new().allow("USDC", "ETH", address);
+        assert!(policy_no_limit.check(&WithdrawRequest::new("USDC", "ETH", address, 1.0), now).is_err());
+
+        let request = WithdrawRequest::new("USDT", "ETH", address, 600.0);
+        policy.reserve(&request, now).unwrap();
+        assert_eq!(policy.remaining_today("USDT", now), 400.0);
+        assert!(policy.reserve(&request, now).is_err());
+
+        policy.release(&request, now);
+        assert_eq!(policy.used_today("USDT", now), 0.0);
+
+        // the limit resets the next utc day
+        policy.reserve(&request, now).unwrap();
+        let tomorrow = now + 24 * 60 * 60 * 1000;
+        policy.reserve(&request, tomorrow).unwrap();
+        assert_eq!(policy.used_today("USDT", tomorrow), 600.0);
+
+        let json = request.clone().fee_included(true).into_json(now as u128).unwrap();
+        assert_eq!(json["amount"], "600");
+        assert_eq!(json["feeType"], 1);
+        assert_eq!(json["accountType"], "FUND");
+        assert_eq!(json["forceChain"], 1);
+
+        let json = request.account_type(WithdrawAccountType::Unified).force_chain(ForceChain::InternalIfPossible).into_json(now as u128).unwrap();
+        assert_eq!(json["accountType"], "UTA");
+        assert_eq!(json["forceChain"], 0);
+
+        // a replacement policy keeps today's usage
+        let mut replacement = WithdrawalPolicy::new().allow("USDT", "ETH", address).daily_limit("USDT", 1000.0);
+        replacement.carry_usage_from(&policy);
+        assert_eq!(replacement.used_today("USDT", tomorrow), 600.0);
+        assert!(replacement.check(&WithdrawRequest::new("USDT", "ETH", address, 600.0), tomorrow).is_err());
+
+        let policy = policy.confirm_with(|request| request.amount < 100.0);
+        let confirm = policy.confirmation().unwrap();
+        assert!(!confirm(&WithdrawRequest::new("USDT", "ETH", address, 600.0)));
+    }
+
+    #[test]
+    pub fn test_coin_metadata_decoding() {
+        let json = r#"{"name":"USDT","coin":"USDT","remainAmount":"150000","chains":[{"chainType":"ERC20","confirmation":"12","withdrawFee":"4","depositMin":"0","withdrawMin":"10","chain":"ETH","chainDeposit":"1","chainWithdraw":"1","minAccuracy":"4","withdrawPercentageFee":"0","contractAddress":"0xdac17f958d2ee523a2206206994597c13d831ec7","safeConfirmNumber":"64"},{"chainType":"TRC20","confirmation":"50","withdrawFee":"","depositMin":"0","withdrawMin":"","chain":"TRX","chainDeposit":"1","chainWithdraw":"0","minAccuracy":"6","withdrawPercentageFee":"0","contractAddress":"TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t","safeConfirmNumber":"100"}]}"#;
+        let coin: CoinMetadata = serde_json::from_str(json).unwrap();
+
+        let eth = coin.chain("ETH").unwrap();
+        assert_eq!(eth.confirmation, 12);
+        assert_eq!(eth.withdraw_precision, 4);
+        assert_eq!(eth.withdraw_cost(100.0), 4.0);
+        assert!(eth.check_deposit().is_ok());
+        assert!(eth.check_withdraw(25.5).is_ok());
+        assert!(eth.check_withdraw(5.0).is_err());
+        assert!(eth.check_withdraw(25.12345).is_err());
+
+        let trx = coin.chain("TRX").unwrap();
+        assert!(!trx.withdraw_enabled);
+        assert_eq!(trx.withdraw_fee, 0.0);
+        assert!(trx.check_withdraw(100.0).is_err());
+        assert!(coin.chain("SOL").is_none());
+
+        // safeConfirmNumber is left empty on chains without one, minAccuracy is always set
+        let coin: CoinMetadata = serde_json::from_str(&json.replace(r#""safeConfirmNumber":"100""#, r#""safeConfirmNumber":"""#)).unwrap();
+        assert_eq!(coin.chain("TRX").unwrap().safe_confirm_number, 0);
+        assert!(serde_json::from_str::<CoinMetadata>(&json.replace(r#""minAccuracy":"6""#, r#""minAccuracy":"""#)).is_err());
+
+        let json = r#"{"coin":"USDT","chains":[{"chainType":"ERC20","addressDeposit":"0xd9e1cd77afa0e50b452a62fbb68a3340602286c3","tagDeposit":"","chain":"ETH","batchReleaseLimit":"-1","contractAddress":"0xdac17f958d2ee523a2206206994597c13d831ec7"}]}"#;
+        let addresses: DepositAddresses = serde_json::from_str(json).unwrap();
+        assert_eq!(addresses.chain("ETH").unwrap().address, "0xd9e1cd77afa0e50b452a62fbb68a3340602286c3");
+    }
+
+    #[test]
+    pub fn test_convert_decoding() {
+        let json = r#"{"quoteTxId":"10100108106409343501030232064","exchangeRate":"0.9998","fromCoin":"USDC","fromCoinType":"crypto","toCoin":"USDT","toCoinType":"crypto","fromAmount":"10","toAmount":"9.998","expiredTime":"1716283350000","requestId":"","extTaxAndFee":[]}"#;
+        let quote: ConvertQuote = serde_json::from_str(json).unwrap();
+        assert_eq!(quote.account_type, ConvertAccountType::Funding);
+        assert_eq!(quote.to_amount, 9.998);
+        assert!(!quote.is_expired(1716283349999));
+        assert!(quote.is_expired(1716283350000));
+        // a quote without expiry must not decode as already expired
+        assert!(serde_json::from_str::<ConvertQuote>(&json.replace(r#""1716283350000""#, r#""""#)).is_err());
+
+        let json = r#"{"accountType":"eb_convert_uta","exchangeTxId":"10100108106409343501030232064","userId":"1","fromCoin":"USDC","fromCoinType":"crypto","toCoin":"USDT","toCoinType":"crypto","fromAmount":"10","toAmount":"9.998","exchangeStatus":"success","extInfo":{"paramType":"","paramValue":""},"convertRate":"0.9998","createdAt":"1716283340000"}"#;
+        let record: ConvertRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(record.account_type, ConvertAccountType::Unified);
+        assert_eq!(record.status, ConvertStatus::Success);
+        assert!(record.status.is_final());
+        assert!(!ConvertStatus::Processing.is_final());
+    }
+
+    #[test]
+    pub fn test_sub_member_and_api_key() {
+        let json = r#"{"uid":"53888001","username":"strategy0001","memberType":1,"status":4,"accountMode":5,"remark":"grid"}"#;
+        let member: SubMember = serde_json::from_str(json).unwrap();
+        assert_eq!(member.member_type, SubMemberType::Normal);
+        assert_eq!(member.status, SubMemberStatus::Frozen);
+        assert_eq!(member.account_mode, Some(5));
+
+        let json = r#"{"id":"16651283","note":"strategy","apiKey":"xxxxx","readOnly":0,"secret":"yyyyy","permissions":{"ContractTrade":["Order","Position"],"Spot":["SpotTrade"],"Wallet":["AccountTransfer"],"Options":[],"Derivatives":[],"CopyTrading":[],"BlockTrade":[],"Exchange":[],"NFT":["NFTQueryProductList"]}}"#;
+        let key: SubApiKey = serde_json::from_str(json).unwrap();
+        assert!(!key.read_only);
+        assert!(key.permissions.can_trade());
+        assert!(!key.permissions.can_withdraw());
+        assert!(key.permissions.contains(ApiPermission::AccountTransfer));
+        assert_eq!(key.permissions.unknown, vec!["NFT:NFTQueryProductList".to_string()]);
+
+        let settings = ApiKeySettings::new("strategy")
+            .ip("1.2.3.4")
+            .ip("5.6.7.8")
+            .permissions(ApiPermissions::new(&[ApiPermission::ContractOrder, ApiPermission::ContractPosition, ApiPermission::SpotTrade]));
+        let json = settings.into_json().unwrap();
+        assert_eq!(json["ips"], "1.2.3.4,5.6.7.8");
+        assert_eq!(json["readOnly"], 0);
+        assert_eq!(json["permissions"]["ContractTrade"], serde_json::json!(["Order", "Position"]));
+        assert_eq!(json["permissions"]["Spot"], serde_json::json!(["SpotTrade"]));
+
+        assert!(ApiKeySettings::new("empty").into_json().is_err());
+        assert!(ApiKeySettings::new("withdraw").read_only(true).permission(ApiPermission::Withdraw).into_json().is_err());
+        assert_eq!(ApiKeySettings::new("unbound").permission(ApiPermission::SpotTrade).into_json().unwrap()["ips"], "*");
+    }
+
+    #[test]
+    pub fn test_preflight_report() {
+        let json = r#"{"id":"13770661","note":"bot","apiKey":"xxxxx","readOnly":0,"secret":"","permissions":{"ContractTrade":["Order","Position"],"Spot":["SpotTrade"],"Wallet":["AccountTransfer","SubMemberTransfer"],"Options":["OptionsTrade"],"Derivatives":[],"CopyTrading":[],"BlockTrade":[],"Exchange":["ExchangeHistory"],"NFT":[]},"ips":["*"],"type":1,"deadlineDay":3,"expiredAt":"2023-12-22T07:20:25Z","createdAt":"2022-10-16T02:24:40Z","unified":0,"uta":0,"userID":24617703,"inviterID":0,"vipLevel":"No VIP","mktMakerLevel":"0","affiliateID":0,"rsaPublicKey":"","isMaster":true,"parentUid":"0","kycLevel":"LEVEL_DEFAULT","kycRegion":""}"#;
+        let key: ApiKeyInfo = serde_json::from_str(json).unwrap();
+        assert!(!key.is_ip_bound());
+        assert_eq!(key.days_to_expiry(), Some(3));
+        assert!(key.is_master);
+
+        // 3 days left is under the default 7
+        let report = PreflightReport::new(key.clone(), 20, &PreflightPolicy::new());
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.ensure_ready().is_err());
+
+        let report = PreflightReport::new(key.clone(), -20, &PreflightPolicy::new().min_days_to_expiry(1));
+        assert!(report.is_ready());
+
+        let policy = PreflightPolicy::new().min_days_to_expiry(1).require_ip_binding(true).max_clock_offset_ms(10);
+        let report = PreflightReport::new(key.clone(), -20, &policy);
+        assert_eq!(report.problems.len(), 2);
+
+        let mut read_only = key;
+        read_only.read_only = true;
+        read_only.ips = vec!["1.2.3.4".to_string()];
+        read_only.expired_at = String::new();
+        assert!(read_only.is_ip_bound());
+        assert_eq!(read_only.days_to_expiry(), None);
+        let report = PreflightReport::new(read_only, 0, &PreflightPolicy::new());
+        assert_eq!(report.problems, vec!["api key is read-only".to_string()]);
+    }
+}
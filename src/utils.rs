@@ -1,13 +1,22 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::env;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use serde::Deserializer;
 use serde_json::Value;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
 pub fn get_timestamp() -> u128 {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
 }
 
+pub fn hmac_sha256_hex(secret_key: &str, payload: &str) -> anyhow::Result<String> {
+    let mut signed_key = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes())?;
+    signed_key.update(payload.as_bytes());
+    Ok(hex::encode(signed_key.finalize().into_bytes()))
+}
+
 pub fn unlock_keys() -> anyhow::Result<(String, String)>{
     let key: String = env::var("bybit_accesskey")?;
     let secret: String = env::var("bybit_secretkey")?;
@@ -19,6 +28,15 @@ pub fn round(x: f64, decimals: u32) -> f64 {
     (x * y).floor() / y
 }
 
+/// Checks `s` is a hyphenated UUID (8-4-4-4-12 hex digits), as bybit requires for transfer ids.
+pub fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    let lengths = [8, 4, 4, 4, 12];
+
+    groups.len() == lengths.len()
+        && groups.iter().zip(lengths).all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 pub fn parse_string_to_f64<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
     D: Deserializer<'de>,
@@ -32,6 +50,18 @@ where
     }
 }
 
+// for amounts bybit leaves empty when there is nothing to report, e.g. fees on non-trade entries
+pub fn parse_string_to_f64_or_zero<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::String(s) if s.is_empty() => Ok(0.0),
+        v => parse_string_to_f64(v).map_err(serde::de::Error::custom),
+    }
+}
+
 pub fn parse_string_to_option_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
 where
     D: Deserializer<'de>,
@@ -49,4 +79,91 @@ where
         Value::Null => Ok(None),
         _ => Err(serde::de::Error::custom("Invalid type")),
     }
-}
\ No newline at end of file
+}
+
+pub fn parse_string_to_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::String(s) => s.parse::<u64>().map_err(serde::de::Error::custom),
+        Value::Number(n) => n.as_u64().ok_or_else(|| serde::de::Error::custom("Invalid number")),
+        _ => Err(serde::de::Error::custom("Invalid type")),
+    }
+}
+
+// for counts bybit leaves empty when they don't apply
+pub fn parse_string_to_u64_or_zero<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::String(s) if s.is_empty() => Ok(0),
+        v => parse_string_to_u64(v).map_err(serde::de::Error::custom),
+    }
+}
+
+pub fn parse_string_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::Bool(b) => Ok(b),
+        Value::String(s) => match s.as_str() {
+            "1" | "true" | "ON" => Ok(true),
+            "0" | "false" | "OFF" | "" => Ok(false),
+            _ => Err(serde::de::Error::custom(format!("Invalid bool {s}"))),
+        },
+        Value::Number(n) => Ok(n.as_i64() == Some(1)),
+        _ => Err(serde::de::Error::custom("Invalid type")),
+    }
+}
+
+// bybit reports unset enum fields as "" (or "UNKNOWN"), map both to None
+pub fn parse_empty_string_to_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::Null => Ok(None),
+        Value::String(ref s) if s.is_empty() || s == "UNKNOWN" => Ok(None),
+        v => serde_json::from_value(v).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+// bybit reports unset int enum fields as 0, map it to None
+pub fn parse_zero_to_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(ref n) if n.as_i64() == Some(0) => Ok(None),
+        v => serde_json::from_value(v).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Splits the inclusive range `[start, end]` (ms) into non-overlapping windows no longer
+/// than `max_window` ms, for endpoints that reject wider startTime/endTime ranges.
+pub fn split_time_range(start: u64, end: u64, max_window: u64) -> Vec<(u64, u64)> {
+    let mut windows = vec![];
+    let mut from = start;
+
+    while from <= end {
+        let to = end.min(from.saturating_add(max_window));
+        windows.push((from, to));
+        if to == u64::MAX {
+            break;
+        }
+        from = to + 1;
+    }
+
+    windows
+}
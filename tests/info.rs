@@ -19,6 +19,7 @@ pub async fn test_get_instrument_all_info() {
 }
 
 #[tokio::test]
+#[allow(unused_must_use)]
 pub async fn test_get_funding_info() {
     let bybit = Bybit::new(None, None, None).unwrap();
 
@@ -41,6 +42,7 @@ pub async fn test_get_instrument_info_all_paginated() {
 }
 
 #[tokio::test]
+#[allow(unused_variables)]
 pub async fn test_get_all_funding_info() {
     let bybit = Bybit::new(None, None, None).unwrap();
 